@@ -0,0 +1,63 @@
+use std::convert::TryFrom;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use dhcp_parser::options::OptionCode;
+use dhcp_parser::packet::{Packet, DHCP_COOKIE};
+use dhcp_parser::view::PacketView;
+
+/// A relayed DHCPREQUEST with a typical option set.
+fn request() -> Vec<u8> {
+    let mut msg = vec![0; 240];
+    msg[..4].copy_from_slice(&[1, 1, 6, 1]);
+    msg[4..8].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+    msg[24..28].copy_from_slice(&[10, 0, 0, 1]);
+    msg[28..34].copy_from_slice(&[0, 0x11, 0x22, 0x33, 0x44, 0x55]);
+    msg[236..240].copy_from_slice(&DHCP_COOKIE);
+    msg.extend_from_slice(&[
+        53, 1, 3, // DHCPMessageType: Request
+        61, 7, 1, 0, 0x11, 0x22, 0x33, 0x44, 0x55, // ClientIdentifier
+        50, 4, 10, 0, 0, 50, // RequestedIPAddress
+        54, 4, 10, 0, 0, 2, // ServerIdentifier
+        57, 2, 5, 220, // MaximumDHCPMessageSize
+        12, 7, b'l', b'a', b'p', b't', b'o', b'p', b'1', // HostName
+        60, 8, b'M', b'S', b'F', b'T', b' ', b'5', b'.', b'0', // VendorClassIdentifier
+        55, 13, 1, 3, 6, 15, 31, 33, 43, 44, 46, 47, 119, 121, 249, // ParameterRequestList
+        82, 12, 1, 4, 0, 1, 0, 5, 2, 4, 0xc0, 0xa8, 0, 1, // RelayAgentInformation
+        255,
+    ]);
+    msg
+}
+
+fn parse(c: &mut Criterion) {
+    let msg = request();
+
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Elements(1));
+
+    group.bench_function("packet", |b| {
+        b.iter(|| {
+            let p = Packet::try_from(black_box(msg.as_slice())).unwrap();
+            black_box(
+                p.options
+                    .get(OptionCode::RelayAgentInformation)
+                    .map(|v| v.len()),
+            )
+        })
+    });
+
+    group.bench_function("view", |b| {
+        b.iter(|| {
+            let view = PacketView::new(black_box(msg.as_slice())).unwrap();
+            black_box(
+                view.option(OptionCode::RelayAgentInformation)
+                    .map(|v| v.len()),
+            )
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
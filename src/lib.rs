@@ -1,17 +1,242 @@
 pub mod options;
 pub mod packet;
+#[cfg(target_os = "linux")]
+mod pktinfo;
+pub mod reply;
+#[cfg(feature = "tokio")]
+pub mod tokio_server;
+pub mod view;
 
+use std::any::Any;
+use std::cell::RefCell;
 use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
 use std::io;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
 
 pub trait PacketHandler {
-    fn handle_packet(&mut self, packet: packet::Packet) -> Option<packet::Packet>;
+    fn handle_packet(
+        &mut self,
+        packet: packet::Packet,
+        context: &RequestContext,
+    ) -> Option<packet::Packet>;
+
+    /// Called once when the server stops, after in-flight packets have been
+    /// handled. Use it to flush state.
+    fn on_shutdown(&mut self) {}
+
+    /// Called for every error while serving. The default ignores it, so
+    /// implement this to log errors.
+    fn on_error(&mut self, _error: &ServerError) {}
+}
+
+/// A handler that worker threads share, so it takes `&self` and keeps any
+/// mutable state behind its own locks.
+///
+/// A `PacketHandler` can be shared by wrapping it in a `Mutex`, at the cost
+/// of handling one packet at a time.
+pub trait SyncPacketHandler: Send + Sync {
+    fn handle_packet(
+        &self,
+        packet: packet::Packet,
+        context: &RequestContext,
+    ) -> Option<packet::Packet>;
+
+    /// Called once when the server stops, after every worker has finished
+    /// its in-flight packet. Use it to flush state.
+    fn on_shutdown(&self) {}
+
+    /// Called for every error while serving, from the worker that hit it.
+    /// The default ignores it, so implement this to log errors.
+    fn on_error(&self, _error: &ServerError) {}
+}
+
+impl<H: PacketHandler + Send> SyncPacketHandler for Mutex<H> {
+    fn handle_packet(
+        &self,
+        packet: packet::Packet,
+        context: &RequestContext,
+    ) -> Option<packet::Packet> {
+        // A handler that panicked is still used for later packets
+        let mut handler = self.lock().unwrap_or_else(|e| e.into_inner());
+        handler.handle_packet(packet, context)
+    }
+
+    fn on_shutdown(&self) {
+        let mut handler = self.lock().unwrap_or_else(|e| e.into_inner());
+        handler.on_shutdown()
+    }
+
+    fn on_error(&self, error: &ServerError) {
+        let mut handler = self.lock().unwrap_or_else(|e| e.into_inner());
+        handler.on_error(error)
+    }
+}
+
+/// An error while serving, reported to the handler's `on_error`.
+///
+/// Only fatal errors stop the server; the rest cost a single packet.
+#[derive(Debug)]
+pub enum ServerError {
+    /// A received datagram is not a valid packet and was dropped.
+    Parse {
+        src: SocketAddr,
+        error: packet::ParseError,
+    },
+    /// The handler's reply could not be encoded and was dropped.
+    Encode {
+        src: SocketAddr,
+        error: packet::EncodeError,
+    },
+    /// Sending a reply failed, e.g. with no route to the destination or a
+    /// firewall rejecting it. The reply was dropped.
+    Send { dst: SocketAddr, error: io::Error },
+    /// Receiving from the socket failed.
+    Receive(io::Error),
+    /// The handler panicked on a packet. The packet was dropped and the
+    /// handler is still used for later ones.
+    Panic { src: SocketAddr, message: String },
+}
+
+impl ServerError {
+    /// Whether the server stops because of this error.
+    ///
+    /// Only receive errors can be fatal. A receive reporting that an
+    /// earlier reply was refused or reset (as Windows does for ICMP port
+    /// unreachable), or that was interrupted, is not.
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            ServerError::Receive(e) => !matches!(
+                e.kind(),
+                io::ErrorKind::ConnectionRefused
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::Interrupted
+            ),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServerError::Parse { src, error } => {
+                write!(f, "dropped packet from {}: {}", src, error)
+            }
+            ServerError::Encode { src, error } => {
+                write!(f, "dropped reply to packet from {}: {}", src, error)
+            }
+            ServerError::Send { dst, error } => {
+                write!(f, "failed to send reply to {}: {}", dst, error)
+            }
+            ServerError::Receive(error) => write!(f, "failed to receive: {}", error),
+            ServerError::Panic { src, message } => {
+                write!(f, "handler panicked on packet from {}: {}", src, message)
+            }
+        }
+    }
+}
+
+impl Error for ServerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ServerError::Parse { error, .. } => Some(error),
+            ServerError::Encode { error, .. } => Some(error),
+            ServerError::Send { error, .. } => Some(error),
+            ServerError::Receive(error) => Some(error),
+            ServerError::Panic { .. } => None,
+        }
+    }
+}
+
+impl From<ServerError> for io::Error {
+    fn from(error: ServerError) -> io::Error {
+        match error {
+            ServerError::Send { error, .. } | ServerError::Receive(error) => error,
+            other => io::Error::new(io::ErrorKind::InvalidData, other),
+        }
+    }
+}
+
+/// Where and when a request arrived, passed to the handler with the packet.
+///
+/// The interface and destination are filled in when the socket supports
+/// it: `UdpSocket` and the tokio server do on Linux for IPv4 sockets.
+#[derive(PartialEq, Clone, Debug)]
+pub struct RequestContext {
+    /// The UDP source address, as returned by `recv_from`.
+    pub src: SocketAddr,
+    /// Index of the interface the packet arrived on, if the socket can
+    /// tell.
+    pub interface: Option<u32>,
+    /// The destination address of the datagram, if the socket can tell.
+    pub dst: Option<IpAddr>,
+    /// When the packet was received.
+    pub received_at: SystemTime,
+}
+
+impl RequestContext {
+    /// A context for a packet from `src` received now, with the interface
+    /// and destination unknown.
+    pub fn new(src: SocketAddr) -> Self {
+        RequestContext {
+            src,
+            interface: None,
+            dst: None,
+            received_at: SystemTime::now(),
+        }
+    }
+
+    /// Whether the packet was sent to the limited broadcast address rather
+    /// than unicast to us. None if the destination is unknown.
+    pub fn is_broadcast(&self) -> Option<bool> {
+        self.dst.map(|dst| match dst {
+            IpAddr::V4(addr) => addr.is_broadcast(),
+            IpAddr::V6(_) => false,
+        })
+    }
 }
 
 pub trait Socket {
     fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)>;
     fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addr: A) -> io::Result<usize>;
+
+    /// Receive a datagram along with what is known about how it arrived.
+    ///
+    /// The default uses `recv_from`, which cannot see the interface or
+    /// destination address. Sockets that can, e.g. through `IP_PKTINFO`,
+    /// should override this to fill them in. `UdpSocket` does so on Linux.
+    fn recv_with_context(&self, buf: &mut [u8]) -> io::Result<(usize, RequestContext)> {
+        let (size, src) = self.recv_from(buf)?;
+        Ok((size, RequestContext::new(src)))
+    }
+
+    /// Ask the socket to report what `recv_with_context` needs, such as
+    /// the interface and destination address. The server calls this before
+    /// receiving.
+    ///
+    /// The default does nothing.
+    fn enable_request_context(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Make `recv_from` give up with `WouldBlock` or `TimedOut` after
+    /// `timeout`, so the server can check for shutdown between packets.
+    ///
+    /// The default does nothing. Sockets that keep it must return one of
+    /// those errors on their own when idle, or shutdown will wait for the
+    /// next packet.
+    fn set_read_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
 }
 
 impl Socket for UdpSocket {
@@ -22,6 +247,100 @@ impl Socket for UdpSocket {
     fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addr: A) -> io::Result<usize> {
         self.send_to(buf, addr)
     }
+
+    #[cfg(target_os = "linux")]
+    fn recv_with_context(&self, buf: &mut [u8]) -> io::Result<(usize, RequestContext)> {
+        pktinfo::recv(self.as_raw_fd(), buf)
+    }
+
+    /// Turns on `IP_PKTINFO` for IPv4 sockets on Linux. Elsewhere the
+    /// interface and destination stay unknown.
+    fn enable_request_context(&self) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            if self.local_addr()?.is_ipv4() {
+                pktinfo::enable(self.as_raw_fd())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.set_read_timeout(timeout)
+    }
+}
+
+/// How often an idle worker checks whether the server is shutting down.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Tells a running server to stop. Clones share the same signal.
+#[derive(Clone, Debug, Default)]
+pub struct ShutdownToken(Arc<AtomicBool>);
+
+impl ShutdownToken {
+    pub fn new() -> Self {
+        ShutdownToken::default()
+    }
+
+    /// Ask the server to stop. Workers finish the packet they are handling
+    /// and exit within `SHUTDOWN_POLL_INTERVAL`.
+    pub fn shutdown(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A server running on its own thread, started with `ServerHandle::start`.
+///
+/// Dropping the handle leaves the server running; call `shutdown` to stop
+/// it.
+pub struct ServerHandle {
+    token: ShutdownToken,
+    thread: thread::JoinHandle<io::Result<()>>,
+}
+
+impl ServerHandle {
+    /// Serve `socket` with `workers` threads in the background. See
+    /// `run_server_with_shutdown` for how the server runs and stops.
+    pub fn start<S, H>(socket: Arc<S>, handler: Arc<H>, workers: u16) -> io::Result<ServerHandle>
+    where
+        S: Socket + Send + Sync + 'static,
+        H: SyncPacketHandler + 'static,
+    {
+        let token = ShutdownToken::new();
+        let server_token = token.clone();
+        let thread = thread::Builder::new()
+            .name("dhcp-server".to_string())
+            .spawn(move || run_server_with_shutdown(&*socket, &*handler, workers, &server_token))?;
+
+        Ok(ServerHandle { token, thread })
+    }
+
+    /// A token that stops this server, e.g. from a signal handler.
+    pub fn shutdown_token(&self) -> ShutdownToken {
+        self.token.clone()
+    }
+
+    /// Whether the server has stopped, by shutdown or by error.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Stop the server and wait for it to drain.
+    pub fn shutdown(self) -> io::Result<()> {
+        self.token.shutdown();
+        self.join()
+    }
+
+    /// Wait for the server to stop, returning its error if it failed.
+    pub fn join(self) -> io::Result<()> {
+        self.thread
+            .join()
+            .unwrap_or_else(|e| std::panic::resume_unwind(e))
+    }
 }
 
 pub fn run_server(handler: &mut impl PacketHandler, workers: u16) -> io::Result<()> {
@@ -30,50 +349,706 @@ pub fn run_server(handler: &mut impl PacketHandler, workers: u16) -> io::Result<
     run_server_with_socket(&socket, handler, workers)
 }
 
+/// Serve requests from `socket` on the calling thread until a fatal error
+/// occurs, handling packets one at a time in the order they arrive.
+///
+/// `workers` is not used, as a `&mut` handler cannot be shared between
+/// threads; use `run_server_threaded_with_socket` for that. Errors are
+/// handled as described for `run_server_with_shutdown`.
 pub fn run_server_with_socket(
     socket: &impl Socket,
     handler: &mut impl PacketHandler,
     _workers: u16,
+) -> io::Result<()> {
+    socket.enable_request_context()?;
+    let handler = Exclusive(RefCell::new(handler));
+    let result = serve(
+        socket,
+        &handler,
+        &ShutdownToken::new(),
+        &AtomicBool::new(false),
+    );
+    handler.on_shutdown();
+    result
+}
+
+pub fn run_server_threaded(handler: &impl SyncPacketHandler, workers: u16) -> io::Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:67")?;
+    socket.set_broadcast(true)?;
+    run_server_threaded_with_socket(&socket, handler, workers)
+}
+
+/// Serve requests from `socket` on `workers` threads until a fatal error
+/// occurs. See `run_server_with_shutdown`.
+pub fn run_server_threaded_with_socket<S, H>(
+    socket: &S,
+    handler: &H,
+    workers: u16,
+) -> io::Result<()>
+where
+    S: Socket + Sync,
+    H: SyncPacketHandler,
+{
+    run_server_with_shutdown(socket, handler, workers, &ShutdownToken::new())
+}
+
+/// Serve requests from `socket` on `workers` threads until `token` is
+/// triggered or a fatal error occurs. Zero workers is treated as one, which
+/// runs on the calling thread.
+///
+/// Each worker receives from the shared socket, handles the packet and
+/// sends the reply before receiving again. With one worker, packets are
+/// handled in the order they arrive. With more, there is no ordering
+/// between packets, even from the same client: a retransmitted REQUEST can
+/// be handled at the same time as the original, and a RELEASE can finish
+/// before the REQUEST sent ahead of it. Handlers that keep per-client state
+/// must serialize it themselves, for example with a lock keyed by
+/// `Packet::client_key`.
+///
+/// The socket's read timeout is set to `SHUTDOWN_POLL_INTERVAL` so idle
+/// workers notice a shutdown. On shutdown, workers finish the packet they
+/// are handling, then `SyncPacketHandler::on_shutdown` is called and this
+/// returns `Ok`.
+///
+/// Every error is passed to `SyncPacketHandler::on_error`. Errors that only
+/// affect one packet, such as a reply that cannot be sent or a panic in
+/// `handle_packet`, drop that packet and serving continues. After a fatal
+/// error (see `ServerError::is_fatal`) the other workers stop the same way
+/// and the error is returned; `on_shutdown` is still called.
+pub fn run_server_with_shutdown<S, H>(
+    socket: &S,
+    handler: &H,
+    workers: u16,
+    token: &ShutdownToken,
+) -> io::Result<()>
+where
+    S: Socket + Sync,
+    H: SyncPacketHandler,
+{
+    socket.enable_request_context()?;
+    socket.set_read_timeout(Some(SHUTDOWN_POLL_INTERVAL))?;
+    let stop = AtomicBool::new(false);
+
+    let result = if workers <= 1 {
+        serve(socket, handler, token, &stop)
+    } else {
+        thread::scope(|scope| {
+            let threads: Vec<_> = (0..workers)
+                .map(|_| scope.spawn(|| serve(socket, handler, token, &stop)))
+                .collect();
+
+            // Handler panics are caught per packet, so a worker only
+            // panics on a bug in the loop itself
+            threads
+                .into_iter()
+                .map(|t| t.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .fold(Ok(()), Result::and)
+        })
+    };
+
+    handler.on_shutdown();
+    result
+}
+
+/// What the receive loop needs from a handler, so the same loop serves a
+/// shared `SyncPacketHandler` and an exclusively borrowed `PacketHandler`.
+trait Dispatch {
+    fn handle_packet(
+        &self,
+        packet: packet::Packet,
+        context: &RequestContext,
+    ) -> Option<packet::Packet>;
+    fn on_shutdown(&self);
+    fn on_error(&self, error: &ServerError);
+}
+
+impl<H: SyncPacketHandler> Dispatch for H {
+    fn handle_packet(
+        &self,
+        packet: packet::Packet,
+        context: &RequestContext,
+    ) -> Option<packet::Packet> {
+        SyncPacketHandler::handle_packet(self, packet, context)
+    }
+
+    fn on_shutdown(&self) {
+        SyncPacketHandler::on_shutdown(self)
+    }
+
+    fn on_error(&self, error: &ServerError) {
+        SyncPacketHandler::on_error(self, error)
+    }
+}
+
+/// A `PacketHandler` borrowed by the single-threaded loop.
+struct Exclusive<'a, H>(RefCell<&'a mut H>);
+
+impl<H: PacketHandler> Dispatch for Exclusive<'_, H> {
+    fn handle_packet(
+        &self,
+        packet: packet::Packet,
+        context: &RequestContext,
+    ) -> Option<packet::Packet> {
+        self.0.borrow_mut().handle_packet(packet, context)
+    }
+
+    fn on_shutdown(&self) {
+        self.0.borrow_mut().on_shutdown()
+    }
+
+    fn on_error(&self, error: &ServerError) {
+        self.0.borrow_mut().on_error(error)
+    }
+}
+
+/// One worker's receive loop. Sets `stop` when it fails so the others
+/// follow.
+fn serve(
+    socket: &impl Socket,
+    handler: &impl Dispatch,
+    token: &ShutdownToken,
+    stop: &AtomicBool,
 ) -> io::Result<()> {
     let mut buf = [0; 1500];
 
-    loop {
-        let (size, src) = socket.recv_from(&mut buf)?;
+    let result = loop {
+        if token.is_shutdown() || stop.load(Ordering::Relaxed) {
+            break Ok(());
+        }
 
-        let src_packet = match packet::Packet::try_from(&buf[..size]) {
-            Ok(p) => p,
+        let (size, context) = match socket.recv_with_context(&mut buf) {
+            Ok(received) => received,
+            Err(e) if is_idle(&e) => continue,
             Err(e) => {
-                eprintln!("{}", e);
+                let error = ServerError::Receive(e);
+                handler.on_error(&error);
+                if error.is_fatal() {
+                    break Err(error.into());
+                }
                 continue;
             }
         };
 
-        process_packet(socket, handler, src_packet, src)?;
-    }
+        if let Some((request, src_packet)) =
+            Request::parse(&buf[..size], context.src, |e| handler.on_error(e))
+        {
+            process_packet(socket, handler, &request, src_packet, &context);
+        }
+    };
+
+    stop.store(true, Ordering::Relaxed);
+    result
 }
 
+/// Whether a receive error only means the read timeout passed.
+fn is_idle(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+/// Handle one packet and send the reply. Failures only affect this packet,
+/// so they go to the handler rather than back to the caller.
 fn process_packet(
     socket: &impl Socket,
-    handler: &mut impl PacketHandler,
+    handler: &impl Dispatch,
+    request: &Request,
     src_packet: packet::Packet,
+    context: &RequestContext,
+) {
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        handler.handle_packet(src_packet, context)
+    }));
+
+    if let Some((data, dst)) = request.reply(outcome, |e| handler.on_error(e)) {
+        if let Err(error) = socket.send_to(data.as_slice(), dst) {
+            handler.on_error(&ServerError::Send { dst, error });
+        }
+    }
+}
+
+/// The message a panic was raised with, if it was a string.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// What the server loops keep about a request while the handler owns the
+/// packet. Both loops parse with `parse` and route with `reply`, and differ
+/// only in how they call the handler and send.
+pub(crate) struct Request {
     src: SocketAddr,
-) -> io::Result<()> {
-    let src_broadcast = src_packet.broadcast_flag();
-    let src_has_giaddr = !src_packet.giaddr.is_unspecified();
+    broadcast: bool,
+    has_giaddr: bool,
+    max_size: usize,
+}
+
+impl Request {
+    /// Parse a datagram received from `src`, reporting it to `on_error` if
+    /// it is malformed.
+    pub(crate) fn parse(
+        data: &[u8],
+        src: SocketAddr,
+        on_error: impl FnOnce(&ServerError),
+    ) -> Option<(Request, packet::Packet)> {
+        match packet::Packet::try_from(data) {
+            Ok(packet) => {
+                let request = Request {
+                    src,
+                    broadcast: packet.broadcast_flag(),
+                    has_giaddr: !packet.giaddr.is_unspecified(),
+                    max_size: packet.max_message_size(),
+                };
+                Some((request, packet))
+            }
+            Err(error) => {
+                on_error(&ServerError::Parse { src, error });
+                None
+            }
+        }
+    }
+
+    /// Encode the handler's reply and choose where to send it. `outcome`
+    /// holds the panic payload if the handler panicked; that, or a reply
+    /// that cannot be encoded, is reported to `on_error` instead.
+    pub(crate) fn reply(
+        &self,
+        outcome: thread::Result<Option<packet::Packet>>,
+        on_error: impl FnOnce(&ServerError),
+    ) -> Option<(Vec<u8>, SocketAddr)> {
+        let reply = match outcome {
+            Ok(reply) => reply?,
+            Err(payload) => {
+                on_error(&ServerError::Panic {
+                    src: self.src,
+                    message: panic_message(payload.as_ref()),
+                });
+                return None;
+            }
+        };
 
-    if let Some(p) = handler.handle_packet(src_packet) {
-        let data: Vec<u8> = (&p).into();
+        let data = match reply.encode(self.max_size) {
+            Ok(data) => data,
+            Err(error) => {
+                on_error(&ServerError::Encode {
+                    src: self.src,
+                    error,
+                });
+                return None;
+            }
+        };
 
-        if !src_has_giaddr && (src.ip().is_unspecified() || src_broadcast) {
-            socket.send_to(
-                data.as_slice(),
-                SocketAddr::new(IpAddr::V4(Ipv4Addr::BROADCAST), src.port()),
-            )?;
+        let src = self.src;
+        let dst = if !self.has_giaddr && (src.ip().is_unspecified() || self.broadcast) {
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::BROADCAST), src.port())
         } else {
             // Has gateway address/unicast to client
-            socket.send_to(data.as_slice(), src)?;
+            src
+        };
+
+        Some((data, dst))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Condvar;
+    use std::time::Duration;
+
+    use crate::options::MessageType;
+
+    /// Hands out queued datagrams. Once empty, every receive fails, or
+    /// times out if `idle` is set.
+    struct MockSocket {
+        incoming: Mutex<VecDeque<Vec<u8>>>,
+        sent: Mutex<Vec<(Vec<u8>, SocketAddr)>>,
+        idle: bool,
+        refuse_broadcast: bool,
+    }
+
+    impl MockSocket {
+        fn new(incoming: Vec<Vec<u8>>) -> Self {
+            MockSocket {
+                incoming: Mutex::new(incoming.into()),
+                sent: Mutex::new(Vec::new()),
+                idle: false,
+                refuse_broadcast: false,
+            }
+        }
+
+        fn idle(incoming: Vec<Vec<u8>>) -> Self {
+            MockSocket {
+                idle: true,
+                ..MockSocket::new(incoming)
+            }
+        }
+    }
+
+    impl Socket for MockSocket {
+        fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+            match self.incoming.lock().unwrap().pop_front() {
+                Some(data) => {
+                    buf[..data.len()].copy_from_slice(&data);
+                    Ok((data.len(), "10.0.0.2:68".parse().unwrap()))
+                }
+                None if self.idle => {
+                    thread::sleep(Duration::from_millis(1));
+                    Err(io::ErrorKind::WouldBlock.into())
+                }
+                None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "done")),
+            }
+        }
+
+        fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addr: A) -> io::Result<usize> {
+            let addr = addr.to_socket_addrs()?.next().unwrap();
+            if self.refuse_broadcast && addr.ip() == Ipv4Addr::BROADCAST {
+                return Err(io::ErrorKind::PermissionDenied.into());
+            }
+            self.sent.lock().unwrap().push((buf.to_vec(), addr));
+            Ok(buf.len())
+        }
+    }
+
+    fn discover(xid: u8) -> Vec<u8> {
+        let mut buf = vec![0; 240];
+        buf[..3].copy_from_slice(&[1, 1, 6]);
+        buf[7] = xid;
+        buf[236..].copy_from_slice(&packet::DHCP_COOKIE);
+        buf.extend_from_slice(&[53, 1, 1, 255]);
+        buf
+    }
+
+    /// Offers to every DISCOVER, holding the first two until both are being
+    /// handled at once.
+    struct Concurrent {
+        active: Mutex<(usize, usize)>,
+        changed: Condvar,
+    }
+
+    impl SyncPacketHandler for Concurrent {
+        fn handle_packet(
+            &self,
+            packet: packet::Packet,
+            _context: &RequestContext,
+        ) -> Option<packet::Packet> {
+            let mut active = self.active.lock().unwrap();
+            active.0 += 1;
+            active.1 = active.1.max(active.0);
+            self.changed.notify_all();
+            let (mut active, _) = self
+                .changed
+                .wait_timeout_while(active, Duration::from_secs(5), |a| a.1 < 2)
+                .unwrap();
+            active.0 -= 1;
+            drop(active);
+
+            packet
+                .reply(MessageType::Offer, Ipv4Addr::new(10, 0, 0, 1))
+                .yiaddr(Ipv4Addr::new(10, 0, 0, 50))
+                .lease_time(3600)
+                .build()
+                .ok()
+        }
+    }
+
+    #[test]
+    fn test_workers() {
+        let socket = MockSocket::new((0..8).map(discover).collect());
+        let handler = Concurrent {
+            active: Mutex::new((0, 0)),
+            changed: Condvar::new(),
+        };
+
+        let err = run_server_threaded_with_socket(&socket, &handler, 4).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        assert!(handler.active.lock().unwrap().1 >= 2);
+        let mut xids: Vec<u8> = socket
+            .sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(data, _)| data[7])
+            .collect();
+        xids.sort_unstable();
+        assert_eq!(xids, (0..8).collect::<Vec<u8>>());
+    }
+
+    struct Counter(Vec<u32>);
+
+    impl PacketHandler for Counter {
+        fn handle_packet(
+            &mut self,
+            packet: packet::Packet,
+            _context: &RequestContext,
+        ) -> Option<packet::Packet> {
+            self.0.push(packet.xid);
+            None
+        }
+    }
+
+    #[test]
+    fn test_single_threaded_order() {
+        let socket = MockSocket::new((0..5).map(discover).collect());
+        let mut handler = Counter(Vec::new());
+
+        assert!(run_server_with_socket(&socket, &mut handler, 4).is_err());
+        assert_eq!(handler.0, [0, 1, 2, 3, 4]);
+        assert!(socket.sent.lock().unwrap().is_empty());
+    }
+
+    /// Replies after a delay and counts shutdowns.
+    struct Slow {
+        started: AtomicBool,
+        shutdowns: Mutex<u32>,
+    }
+
+    impl SyncPacketHandler for Slow {
+        fn handle_packet(
+            &self,
+            packet: packet::Packet,
+            _context: &RequestContext,
+        ) -> Option<packet::Packet> {
+            self.started.store(true, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(20));
+            packet
+                .reply(MessageType::Offer, Ipv4Addr::new(10, 0, 0, 1))
+                .yiaddr(Ipv4Addr::new(10, 0, 0, 50))
+                .lease_time(3600)
+                .build()
+                .ok()
+        }
+
+        fn on_shutdown(&self) {
+            *self.shutdowns.lock().unwrap() += 1;
+        }
+    }
+
+    #[test]
+    fn test_shutdown() {
+        for workers in (1..=4).cycle().take(20) {
+            let socket = Arc::new(MockSocket::idle(vec![discover(1)]));
+            let handler = Arc::new(Slow {
+                started: AtomicBool::new(false),
+                shutdowns: Mutex::new(0),
+            });
+            let server = ServerHandle::start(socket.clone(), handler.clone(), workers).unwrap();
+
+            // Stop while the packet is still being handled
+            while !handler.started.load(Ordering::SeqCst) {
+                thread::yield_now();
+            }
+            server.shutdown().unwrap();
+
+            assert_eq!(socket.sent.lock().unwrap().len(), 1);
+            assert_eq!(*handler.shutdowns.lock().unwrap(), 1);
+        }
+    }
+
+    #[test]
+    fn test_shutdown_token() {
+        let socket = Arc::new(MockSocket::idle(Vec::new()));
+        let handler = Arc::new(Mutex::new(Counter(Vec::new())));
+        let server = ServerHandle::start(socket, handler, 2).unwrap();
+
+        let token = server.shutdown_token();
+        assert!(!server.is_finished());
+        token.shutdown();
+        assert!(server.join().is_ok());
+        assert!(token.is_shutdown());
+    }
+
+    /// Offers to every DISCOVER and records the errors it is told about.
+    struct Recorder(Vec<String>);
+
+    impl PacketHandler for Recorder {
+        fn handle_packet(
+            &mut self,
+            packet: packet::Packet,
+            _context: &RequestContext,
+        ) -> Option<packet::Packet> {
+            packet
+                .reply(MessageType::Offer, Ipv4Addr::new(10, 0, 0, 1))
+                .yiaddr(Ipv4Addr::new(10, 0, 0, 50))
+                .lease_time(3600)
+                .build()
+                .ok()
+        }
+
+        fn on_error(&mut self, error: &ServerError) {
+            self.0.push(error.to_string());
+        }
+    }
+
+    #[test]
+    fn test_errors_per_packet() {
+        let mut broadcast = discover(1);
+        broadcast[10] = 0x80;
+        let socket = MockSocket {
+            refuse_broadcast: true,
+            ..MockSocket::new(vec![broadcast, vec![0; 10], discover(2)])
+        };
+        let mut handler = Recorder(Vec::new());
+
+        // Only running out of packets stops the server
+        let err = run_server_with_socket(&socket, &mut handler, 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let sent = socket.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0[7], 2);
+
+        let errors = handler.0;
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("failed to send reply to 255.255.255.255:68"));
+        assert!(errors[1].starts_with("dropped packet from 10.0.0.2:68"));
+        assert!(errors[2].starts_with("failed to receive"));
+    }
+
+    /// Panics on the packet with xid 1 and records the errors it is told
+    /// about.
+    struct Panicky(Mutex<Vec<String>>);
+
+    impl SyncPacketHandler for Panicky {
+        fn handle_packet(
+            &self,
+            packet: packet::Packet,
+            _context: &RequestContext,
+        ) -> Option<packet::Packet> {
+            assert_ne!(packet.xid, 1, "bad packet");
+            packet
+                .reply(MessageType::Offer, Ipv4Addr::new(10, 0, 0, 1))
+                .yiaddr(Ipv4Addr::new(10, 0, 0, 50))
+                .lease_time(3600)
+                .build()
+                .ok()
+        }
+
+        fn on_error(&self, error: &ServerError) {
+            self.0.lock().unwrap().push(error.to_string());
+        }
+    }
+
+    #[test]
+    fn test_handler_panic() {
+        let socket = MockSocket::new((0..6).map(discover).collect());
+        let handler = Panicky(Mutex::new(Vec::new()));
+
+        let err = run_server_threaded_with_socket(&socket, &handler, 3).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(socket.sent.lock().unwrap().len(), 5);
+
+        let errors = handler.0.into_inner().unwrap();
+        let panics: Vec<_> = errors
+            .iter()
+            .filter(|e| e.starts_with("handler panicked on packet from 10.0.0.2:68"))
+            .collect();
+        assert_eq!(panics.len(), 1);
+        assert!(panics[0].contains("bad packet"));
+    }
+
+    #[test]
+    fn test_error_classification() {
+        let receive = |kind: io::ErrorKind| ServerError::Receive(kind.into());
+        assert!(receive(io::ErrorKind::Other).is_fatal());
+        assert!(!receive(io::ErrorKind::ConnectionReset).is_fatal());
+        assert!(!receive(io::ErrorKind::Interrupted).is_fatal());
+
+        let send = ServerError::Send {
+            dst: "10.0.0.2:68".parse().unwrap(),
+            error: io::ErrorKind::PermissionDenied.into(),
+        };
+        assert!(!send.is_fatal());
+        assert_eq!(
+            io::Error::from(send).kind(),
+            io::ErrorKind::PermissionDenied
+        );
+    }
+
+    /// Reports the interface and destination as an `IP_PKTINFO` socket
+    /// would.
+    struct PktInfoSocket(MockSocket);
+
+    impl Socket for PktInfoSocket {
+        fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+            self.0.recv_from(buf)
+        }
+
+        fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addr: A) -> io::Result<usize> {
+            self.0.send_to(buf, addr)
+        }
+
+        fn recv_with_context(&self, buf: &mut [u8]) -> io::Result<(usize, RequestContext)> {
+            let (size, src) = self.recv_from(buf)?;
+            let context = RequestContext {
+                interface: Some(3),
+                dst: Some(Ipv4Addr::BROADCAST.into()),
+                ..RequestContext::new(src)
+            };
+            Ok((size, context))
+        }
+    }
+
+    struct Contexts(Vec<RequestContext>);
+
+    impl PacketHandler for Contexts {
+        fn handle_packet(
+            &mut self,
+            _packet: packet::Packet,
+            context: &RequestContext,
+        ) -> Option<packet::Packet> {
+            self.0.push(context.clone());
+            None
         }
     }
 
-    Ok(())
+    #[test]
+    fn test_request_context() {
+        let before = SystemTime::now();
+        let socket = MockSocket::new(vec![discover(1)]);
+        let mut handler = Contexts(Vec::new());
+        assert!(run_server_with_socket(&socket, &mut handler, 1).is_err());
+
+        let contexts = handler.0;
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(contexts[0].src, "10.0.0.2:68".parse().unwrap());
+        assert_eq!(contexts[0].interface, None);
+        assert_eq!(contexts[0].is_broadcast(), None);
+        assert!(contexts[0].received_at >= before);
+
+        let socket = PktInfoSocket(MockSocket::new(vec![discover(1)]));
+        let mut handler = Contexts(Vec::new());
+        assert!(run_server_with_socket(&socket, &mut handler, 1).is_err());
+
+        let contexts = handler.0;
+        assert_eq!(contexts[0].interface, Some(3));
+        assert_eq!(contexts[0].is_broadcast(), Some(true));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pktinfo() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.enable_request_context().unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client
+            .send_to(b"ping", server.local_addr().unwrap())
+            .unwrap();
+
+        let mut buf = [0; 16];
+        let (size, context) = server.recv_with_context(&mut buf).unwrap();
+        assert_eq!(&buf[..size], b"ping");
+        assert_eq!(context.src, client.local_addr().unwrap());
+        assert!(context.interface.is_some_and(|i| i > 0));
+        assert_eq!(context.dst, Some(Ipv4Addr::LOCALHOST.into()));
+        assert_eq!(context.is_broadcast(), Some(false));
+    }
 }
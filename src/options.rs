@@ -1,8 +1,34 @@
 use std::convert::TryFrom;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::str::FromStr;
+
+mod client_id;
+mod dns;
+mod fqdn;
+mod pxe;
+mod relay;
+mod route;
+mod space;
+mod typed;
+mod vendor;
+
+pub use self::client_id::{ClientIdentifier, ClientKey, Duid};
+pub use self::dns::DnsName;
+pub use self::fqdn::ClientFqdn;
+pub use self::pxe::{ClientNetworkInterface, ProcessorArchitecture, Uuid};
+pub use self::relay::{RelayAgentInformation, RelaySubOption};
+pub use self::route::ClasslessRoute;
+pub use self::space::{
+    OptionSpace, OptionSpaceRegistry, SubOptionKind, SubOptionValue, VendorSubOption,
+};
+pub use self::typed::{DhcpOption, OptionError};
+pub use self::vendor::{VendorClass, VendorOptions};
 
 #[repr(u8)]
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug, Copy)]
 pub enum MessageType {
     Discover = 1,
     Offer = 2,
@@ -53,10 +79,101 @@ impl fmt::Display for MessageType {
     }
 }
 
+/// Value of the Option Overload option (52), naming which of the BOOTP
+/// `file` and `sname` fields carry options instead of strings.
 #[repr(u8)]
-#[derive(PartialEq, Hash, Eq, Clone, Debug, Copy)]
-pub enum OptionCode {
-    End = 255,
+#[derive(PartialEq, Clone, Debug, Copy)]
+pub enum Overload {
+    File = 1,
+    Sname = 2,
+    Both = 3,
+}
+
+impl Overload {
+    /// Returns true if the `file` field holds options.
+    pub fn file(self) -> bool {
+        self != Overload::Sname
+    }
+
+    /// Returns true if the `sname` field holds options.
+    pub fn sname(self) -> bool {
+        self != Overload::File
+    }
+}
+
+impl TryFrom<u8> for Overload {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Overload::File),
+            2 => Ok(Overload::Sname),
+            3 => Ok(Overload::Both),
+            _ => Err("overload value out of range"),
+        }
+    }
+}
+
+/// Declares `OptionCode` from a table of names and wire values, along with
+/// its conversions to and from `u8`, `Display` and the name lookup used by
+/// `FromStr`.
+macro_rules! option_codes {
+    ($($(#[$doc:meta])* $name:ident = $value:expr,)*) => {
+        /// DHCP option codes, from the IANA BOOTP/DHCP parameter registry.
+        ///
+        /// Codes that are not in the registry below are carried as `Other` so
+        /// they survive parsing and re-encoding. Equality and hashing are by
+        /// wire value, so `OptionCode::Other(1)` is the same code as
+        /// `OptionCode::SubnetMask`. Codes 128-135, which the registry lists
+        /// as PXE codes with vendor-specific meanings, are left as `Other`.
+        #[derive(Clone, Debug, Copy)]
+        pub enum OptionCode {
+            $($(#[$doc])* $name,)*
+            /// Any code not listed above, such as unassigned codes and most
+            /// site-specific codes 224-254.
+            Other(u8),
+        }
+
+        impl OptionCode {
+            /// The registered name, as printed by `Display`.
+            fn name(&self) -> Option<&'static str> {
+                match self {
+                    $(OptionCode::$name => Some(stringify!($name)),)*
+                    OptionCode::Other(_) => None,
+                }
+            }
+
+            fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $(stringify!($name) => Some(OptionCode::$name),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl TryFrom<u8> for OptionCode {
+            type Error = &'static str;
+
+            fn try_from(code: u8) -> Result<Self, Self::Error> {
+                match code {
+                    $($value => Ok(OptionCode::$name),)*
+                    _ => Err("option code out of range"),
+                }
+            }
+        }
+
+        impl From<OptionCode> for u8 {
+            fn from(code: OptionCode) -> u8 {
+                match code {
+                    $(OptionCode::$name => $value,)*
+                    OptionCode::Other(c) => c,
+                }
+            }
+        }
+    };
+}
+
+option_codes! {
     Pad = 0,
     SubnetMask = 1,
     TimeOffset = 2,
@@ -117,19 +234,6 @@ pub enum OptionCode {
     NetBIOSOverTCPIPScope = 47,
     XWindowSystemFontServer = 48,
     XWindowSystemDisplayManager = 49,
-    NetworkInformationServicePlusDomain = 64,
-    NetworkInformationServicePlusServers = 65,
-    MobileIPHomeAgent = 68,
-    SimpleMailTransportProtocol = 69,
-    PostOfficeProtocolServer = 70,
-    NetworkNewsTransportProtocol = 71,
-    DefaultWorldWideWebServer = 72,
-    DefaultFingerServer = 73,
-    DefaultInternetRelayChatServer = 74,
-    StreetTalkServer = 75,
-    StreetTalkDirectoryAssistance = 76,
-
-    RelayAgentInformation = 82,
 
     // DHCP Extensions
     RequestedIPAddress = 50,
@@ -144,205 +248,350 @@ pub enum OptionCode {
     RebindingTimeValue = 59,
     VendorClassIdentifier = 60,
     ClientIdentifier = 61,
-
+    NetWareIPDomain = 62,
+    NetWareIPInformation = 63,
+    NetworkInformationServicePlusDomain = 64,
+    NetworkInformationServicePlusServers = 65,
     TFTPServerName = 66,
     BootFileName = 67,
+    MobileIPHomeAgent = 68,
+    SimpleMailTransportProtocol = 69,
+    PostOfficeProtocolServer = 70,
+    NetworkNewsTransportProtocol = 71,
+    DefaultWorldWideWebServer = 72,
+    DefaultFingerServer = 73,
+    DefaultInternetRelayChatServer = 74,
+    StreetTalkServer = 75,
+    StreetTalkDirectoryAssistance = 76,
 
+    // Later extensions
     UserClass = 77,
-
+    SLPDirectoryAgent = 78,
+    SLPServiceScope = 79,
+    RapidCommit = 80,
+    ClientFQDN = 81,
+    RelayAgentInformation = 82,
+    InternetStorageNameService = 83,
+    NDSServers = 85,
+    NDSTreeName = 86,
+    NDSContext = 87,
+    BCMCSControllerDomainNameList = 88,
+    BCMCSControllerIPv4Address = 89,
+    Authentication = 90,
+    ClientLastTransactionTime = 91,
+    AssociatedIP = 92,
     ClientArchitecture = 93,
-
+    ClientNetworkInterfaceIdentifier = 94,
+    LDAP = 95,
+    ClientMachineIdentifier = 97,
+    UserAuthentication = 98,
+    GeoConfCivic = 99,
     TZPOSIXString = 100,
     TZDatabaseString = 101,
-
+    IPv6OnlyPreferred = 108,
+    DHCP4o6S46SourceAddress = 109,
+    NetInfoAddress = 112,
+    NetInfoTag = 113,
+    CaptivePortal = 114,
+    AutoConfigure = 116,
+    NameServiceSearch = 117,
+    SubnetSelection = 118,
+    DomainSearch = 119,
+    SIPServers = 120,
     ClasslessRouteFormat = 121,
+    CableLabsClientConfiguration = 122,
+    GeoConf = 123,
+    VendorIdentifyingVendorClass = 124,
+    VendorIdentifyingVendorSpecificInformation = 125,
+    PANAAgent = 136,
+    LoSTServer = 137,
+    CAPWAPAccessController = 138,
+    MoSIPv4Address = 139,
+    MoSFQDN = 140,
+    SIPUAConfigurationServiceDomains = 141,
+    ANDSFIPv4Address = 142,
+    SZTPRedirect = 143,
+    GeoLoc = 144,
+    ForceRenewNonceCapable = 145,
+    RDNSSSelection = 146,
+    DOTSReferenceIdentifier = 147,
+    DOTSAddress = 148,
+    TFTPServerAddress = 150,
+    StatusCode = 151,
+    BaseTime = 152,
+    StartTimeOfState = 153,
+    QueryStartTime = 154,
+    QueryEndTime = 155,
+    DHCPState = 156,
+    DataSource = 157,
+    PCPServer = 158,
+    PortParameters = 159,
+    MUDURL = 161,
+    EncryptedDNSServer = 162,
+    PXELinuxMagic = 208,
+    ConfigurationFile = 209,
+    PathPrefix = 210,
+    RebootTime = 211,
+    IPv6RapidDeployment = 212,
+    AccessNetworkDomainName = 213,
+    SubnetAllocation = 220,
+    VirtualSubnetSelection = 221,
+    /// Microsoft's pre-standard copy of `ClasslessRouteFormat`.
+    MSClasslessRouteFormat = 249,
+    /// Web Proxy Auto-Discovery URL, an unregistered but widely used code.
+    WebProxyAutoDiscovery = 252,
+    End = 255,
 }
 
-impl TryFrom<u8> for OptionCode {
-    type Error = &'static str;
+impl OptionCode {
+    /// Map a wire value to its option code, falling back to `Other` for
+    /// codes that are not in the registry.
+    pub fn from_u8(code: u8) -> Self {
+        OptionCode::try_from(code).unwrap_or(OptionCode::Other(code))
+    }
+}
 
-    fn try_from(code: u8) -> Result<Self, Self::Error> {
-        match code {
-            255 => Ok(OptionCode::End),
-            0 => Ok(OptionCode::Pad),
-            1 => Ok(OptionCode::SubnetMask),
-            2 => Ok(OptionCode::TimeOffset),
-            3 => Ok(OptionCode::Router),
-            4 => Ok(OptionCode::TimeServer),
-            5 => Ok(OptionCode::NameServer),
-            6 => Ok(OptionCode::DomainNameServer),
-            7 => Ok(OptionCode::LogServer),
-            8 => Ok(OptionCode::CookieServer),
-            9 => Ok(OptionCode::LPRServer),
-            10 => Ok(OptionCode::ImpressServer),
-            11 => Ok(OptionCode::ResourceLocationServer),
-            12 => Ok(OptionCode::HostName),
-            13 => Ok(OptionCode::BootFileSize),
-            14 => Ok(OptionCode::MeritDumpFile),
-            15 => Ok(OptionCode::DomainName),
-            16 => Ok(OptionCode::SwapServer),
-            17 => Ok(OptionCode::RootPath),
-            18 => Ok(OptionCode::ExtensionsPath),
-            19 => Ok(OptionCode::IPForwardingEnableDisable),
-            20 => Ok(OptionCode::NonLocalSourceRoutingEnableDisable),
-            21 => Ok(OptionCode::PolicyFilter),
-            22 => Ok(OptionCode::MaximumDatagramReassemblySize),
-            23 => Ok(OptionCode::DefaultIPTimeToLive),
-            24 => Ok(OptionCode::PathMTUAgingTimeout),
-            25 => Ok(OptionCode::PathMTUPlateauTable),
-            26 => Ok(OptionCode::InterfaceMTU),
-            27 => Ok(OptionCode::AllSubnetsAreLocal),
-            28 => Ok(OptionCode::BroadcastAddress),
-            29 => Ok(OptionCode::PerformMaskDiscovery),
-            30 => Ok(OptionCode::MaskSupplier),
-            31 => Ok(OptionCode::PerformRouterDiscovery),
-            32 => Ok(OptionCode::RouterSolicitationAddress),
-            33 => Ok(OptionCode::StaticRoute),
-            34 => Ok(OptionCode::TrailerEncapsulation),
-            35 => Ok(OptionCode::ARPCacheTimeout),
-            36 => Ok(OptionCode::EthernetEncapsulation),
-            37 => Ok(OptionCode::TCPDefaultTTL),
-            38 => Ok(OptionCode::TCPKeepaliveInterval),
-            39 => Ok(OptionCode::TCPKeepaliveGarbage),
-            40 => Ok(OptionCode::NetworkInformationServiceDomain),
-            41 => Ok(OptionCode::NetworkInformationServers),
-            42 => Ok(OptionCode::NetworkTimeProtocolServers),
-            43 => Ok(OptionCode::VendorSpecificInformation),
-            44 => Ok(OptionCode::NetBIOSOverTCPIPNameServer),
-            45 => Ok(OptionCode::NetBIOSOverTCPIPDatagramDistributionServer),
-            46 => Ok(OptionCode::NetBIOSOverTCPIPNodeType),
-            47 => Ok(OptionCode::NetBIOSOverTCPIPScope),
-            48 => Ok(OptionCode::XWindowSystemFontServer),
-            49 => Ok(OptionCode::XWindowSystemDisplayManager),
-            64 => Ok(OptionCode::NetworkInformationServicePlusDomain),
-            65 => Ok(OptionCode::NetworkInformationServicePlusServers),
-            68 => Ok(OptionCode::MobileIPHomeAgent),
-            69 => Ok(OptionCode::SimpleMailTransportProtocol),
-            70 => Ok(OptionCode::PostOfficeProtocolServer),
-            71 => Ok(OptionCode::NetworkNewsTransportProtocol),
-            72 => Ok(OptionCode::DefaultWorldWideWebServer),
-            73 => Ok(OptionCode::DefaultFingerServer),
-            74 => Ok(OptionCode::DefaultInternetRelayChatServer),
-            75 => Ok(OptionCode::StreetTalkServer),
-            76 => Ok(OptionCode::StreetTalkDirectoryAssistance),
-            82 => Ok(OptionCode::RelayAgentInformation),
-            50 => Ok(OptionCode::RequestedIPAddress),
-            51 => Ok(OptionCode::IPAddressLeaseTime),
-            52 => Ok(OptionCode::Overload),
-            53 => Ok(OptionCode::DHCPMessageType),
-            54 => Ok(OptionCode::ServerIdentifier),
-            55 => Ok(OptionCode::ParameterRequestList),
-            56 => Ok(OptionCode::Message),
-            57 => Ok(OptionCode::MaximumDHCPMessageSize),
-            58 => Ok(OptionCode::RenewalTimeValue),
-            59 => Ok(OptionCode::RebindingTimeValue),
-            60 => Ok(OptionCode::VendorClassIdentifier),
-            61 => Ok(OptionCode::ClientIdentifier),
-            66 => Ok(OptionCode::TFTPServerName),
-            67 => Ok(OptionCode::BootFileName),
-            77 => Ok(OptionCode::UserClass),
-            93 => Ok(OptionCode::ClientArchitecture),
-            100 => Ok(OptionCode::TZPOSIXString),
-            101 => Ok(OptionCode::TZDatabaseString),
-            121 => Ok(OptionCode::ClasslessRouteFormat),
-            _ => Err("option code out of range"),
-        }
+impl PartialEq for OptionCode {
+    fn eq(&self, other: &Self) -> bool {
+        u8::from(*self) == u8::from(*other)
+    }
+}
+
+impl Eq for OptionCode {}
+
+impl Hash for OptionCode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        u8::from(*self).hash(state);
     }
 }
 
 impl fmt::Display for OptionCode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                OptionCode::Pad => "Pad",
-                OptionCode::SubnetMask => "SubnetMask",
-                OptionCode::TimeOffset => "TimeOffset",
-                OptionCode::Router => "Router",
-                OptionCode::TimeServer => "TimeServer",
-                OptionCode::NameServer => "NameServer",
-                OptionCode::DomainNameServer => "DomainNameServer",
-                OptionCode::LogServer => "LogServer",
-                OptionCode::CookieServer => "CookieServer",
-                OptionCode::LPRServer => "LPRServer",
-                OptionCode::ImpressServer => "ImpressServer",
-                OptionCode::ResourceLocationServer => "ResourceLocationServer",
-                OptionCode::HostName => "HostName",
-                OptionCode::BootFileSize => "BootFileSize",
-                OptionCode::MeritDumpFile => "MeritDumpFile",
-                OptionCode::DomainName => "DomainName",
-                OptionCode::SwapServer => "SwapServer",
-                OptionCode::RootPath => "RootPath",
-                OptionCode::ExtensionsPath => "ExtensionsPath",
-                OptionCode::IPForwardingEnableDisable => "IPForwardingEnableDisable",
-                OptionCode::NonLocalSourceRoutingEnableDisable =>
-                    "NonLocalSourceRoutingEnableDisable",
-                OptionCode::PolicyFilter => "PolicyFilter",
-                OptionCode::MaximumDatagramReassemblySize => "MaximumDatagramReassemblySize",
-                OptionCode::DefaultIPTimeToLive => "DefaultIPTimeToLive",
-                OptionCode::PathMTUAgingTimeout => "PathMTUAgingTimeout",
-                OptionCode::PathMTUPlateauTable => "PathMTUPlateauTable",
-                OptionCode::InterfaceMTU => "InterfaceMTU",
-                OptionCode::AllSubnetsAreLocal => "AllSubnetsAreLocal",
-                OptionCode::BroadcastAddress => "BroadcastAddress",
-                OptionCode::PerformMaskDiscovery => "PerformMaskDiscovery",
-                OptionCode::MaskSupplier => "MaskSupplier",
-                OptionCode::PerformRouterDiscovery => "PerformRouterDiscovery",
-                OptionCode::RouterSolicitationAddress => "RouterSolicitationAddress",
-                OptionCode::StaticRoute => "StaticRoute",
-                OptionCode::TrailerEncapsulation => "TrailerEncapsulation",
-                OptionCode::ARPCacheTimeout => "ARPCacheTimeout",
-                OptionCode::EthernetEncapsulation => "EthernetEncapsulation",
-                OptionCode::TCPDefaultTTL => "TCPDefaultTTL",
-                OptionCode::TCPKeepaliveInterval => "TCPKeepaliveInterval",
-                OptionCode::TCPKeepaliveGarbage => "TCPKeepaliveGarbage",
-                OptionCode::NetworkInformationServiceDomain => "NetworkInformationServiceDomain",
-                OptionCode::NetworkInformationServers => "NetworkInformationServers",
-                OptionCode::NetworkTimeProtocolServers => "NetworkTimeProtocolServers",
-                OptionCode::VendorSpecificInformation => "VendorSpecificInformation",
-                OptionCode::NetBIOSOverTCPIPNameServer => "NetBIOSOverTCPIPNameServer",
-                OptionCode::NetBIOSOverTCPIPDatagramDistributionServer =>
-                    "NetBIOSOverTCPIPDatagramDistributionServer",
-                OptionCode::NetBIOSOverTCPIPNodeType => "NetBIOSOverTCPIPNodeType",
-                OptionCode::NetBIOSOverTCPIPScope => "NetBIOSOverTCPIPScope",
-                OptionCode::XWindowSystemFontServer => "XWindowSystemFontServer",
-                OptionCode::XWindowSystemDisplayManager => "XWindowSystemDisplayManager",
-                OptionCode::NetworkInformationServicePlusDomain =>
-                    "NetworkInformationServicePlusDomain",
-                OptionCode::NetworkInformationServicePlusServers =>
-                    "NetworkInformationServicePlusServers",
-                OptionCode::MobileIPHomeAgent => "MobileIPHomeAgent",
-                OptionCode::SimpleMailTransportProtocol => "SimpleMailTransportProtocol",
-                OptionCode::PostOfficeProtocolServer => "PostOfficeProtocolServer",
-                OptionCode::NetworkNewsTransportProtocol => "NetworkNewsTransportProtocol",
-                OptionCode::DefaultWorldWideWebServer => "DefaultWorldWideWebServer",
-                OptionCode::DefaultFingerServer => "DefaultFingerServer",
-                OptionCode::DefaultInternetRelayChatServer => "DefaultInternetRelayChatServer",
-                OptionCode::StreetTalkServer => "StreetTalkServer",
-                OptionCode::StreetTalkDirectoryAssistance => "StreetTalkDirectoryAssistance",
-                OptionCode::RelayAgentInformation => "RelayAgentInformation",
-                OptionCode::RequestedIPAddress => "RequestedIPAddress",
-                OptionCode::IPAddressLeaseTime => "IPAddressLeaseTime",
-                OptionCode::Overload => "Overload",
-                OptionCode::DHCPMessageType => "DHCPMessageType",
-                OptionCode::ServerIdentifier => "ServerIdentifier",
-                OptionCode::ParameterRequestList => "ParameterRequestList",
-                OptionCode::Message => "Message",
-                OptionCode::MaximumDHCPMessageSize => "MaximumDHCPMessageSize",
-                OptionCode::RenewalTimeValue => "RenewalTimeValue",
-                OptionCode::RebindingTimeValue => "RebindingTimeValue",
-                OptionCode::VendorClassIdentifier => "VendorClassIdentifier",
-                OptionCode::ClientIdentifier => "ClientIdentifier",
-                OptionCode::TFTPServerName => "TFTPServerName",
-                OptionCode::BootFileName => "BootFileName",
-                OptionCode::UserClass => "UserClass",
-                OptionCode::ClientArchitecture => "ClientArchitecture",
-                OptionCode::TZPOSIXString => "TZPOSIXString",
-                OptionCode::TZDatabaseString => "TZDatabaseString",
-                OptionCode::ClasslessRouteFormat => "ClasslessRouteFormat",
-                OptionCode::End => "End",
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "Other({})", u8::from(*self)),
+        }
+    }
+}
+
+/// Parses a registered name such as `DomainNameServer`, a decimal code such
+/// as `6`, or the `Other(n)` form printed by `Display`. Numbers outside the
+/// registry give `Other`.
+impl FromStr for OptionCode {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = s
+            .strip_prefix("Other(")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .unwrap_or(s);
+
+        match number.parse::<u8>() {
+            Ok(code) => Ok(OptionCode::from_u8(code)),
+            Err(_) => OptionCode::from_name(s).ok_or("unknown option code"),
+        }
+    }
+}
+
+/// The options carried by a packet, kept in the order they were added or
+/// appeared on the wire.
+///
+/// A code normally appears once; `insert`, `get` and `remove` work on that
+/// single value. `push` adds a further instance of a code that is already
+/// present. Per RFC 3396 a receiver concatenates repeated instances, which
+/// is also what the parser does, so parsed options always hold one entry
+/// per code.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Options {
+    entries: Vec<(OptionCode, Vec<u8>)>,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, code: OptionCode) -> bool {
+        self.entries.iter().any(|(c, _)| *c == code)
+    }
+
+    /// Value of the first instance of `code`.
+    pub fn get(&self, code: OptionCode) -> Option<&[u8]> {
+        self.entries
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, v)| v.as_slice())
+    }
+
+    /// Mutable value of the first instance of `code`.
+    pub fn get_mut(&mut self, code: OptionCode) -> Option<&mut Vec<u8>> {
+        self.entries
+            .iter_mut()
+            .find(|(c, _)| *c == code)
+            .map(|(_, v)| v)
+    }
+
+    /// Values of every instance of `code`, in order.
+    pub fn get_all(&self, code: OptionCode) -> impl Iterator<Item = &[u8]> {
+        self.entries
+            .iter()
+            .filter(move |(c, _)| *c == code)
+            .map(|(_, v)| v.as_slice())
+    }
+
+    /// Set the value of `code`. An existing option keeps its position and
+    /// any further instances of it are dropped; a new one is appended.
+    /// Returns the previous value of the first instance.
+    pub fn insert(&mut self, code: OptionCode, value: Vec<u8>) -> Option<Vec<u8>> {
+        match self.entries.iter().position(|(c, _)| *c == code) {
+            Some(i) => {
+                let old = std::mem::replace(&mut self.entries[i].1, value);
+                let mut seen = 0;
+                self.entries.retain(|(c, _)| {
+                    if *c == code {
+                        seen += 1;
+                        seen == 1
+                    } else {
+                        true
+                    }
+                });
+                Some(old)
             }
-        )
+            None => {
+                self.entries.push((code, value));
+                None
+            }
+        }
+    }
+
+    /// Append an instance of `code`, even if the code is already present.
+    pub fn push(&mut self, code: OptionCode, value: Vec<u8>) {
+        self.entries.push((code, value));
+    }
+
+    /// Decode the value of `code` into its typed form.
+    pub fn get_decoded(&self, code: OptionCode) -> Option<Result<DhcpOption, OptionError>> {
+        self.get(code).map(|v| DhcpOption::decode(code, v))
+    }
+
+    /// Encode and set a typed option, as with `insert`. Nothing is set if
+    /// the option cannot be encoded.
+    pub fn insert_decoded(&mut self, option: DhcpOption) -> Result<Option<Vec<u8>>, OptionError> {
+        Ok(self.insert(option.code(), option.encode()?))
+    }
+
+    /// Remove every instance of `code`, returning the value of the first.
+    pub fn remove(&mut self, code: OptionCode) -> Option<Vec<u8>> {
+        let i = self.entries.iter().position(|(c, _)| *c == code)?;
+        let (_, old) = self.entries.remove(i);
+        self.entries.retain(|(c, _)| *c != code);
+        Some(old)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Iterate over the options in order.
+    pub fn iter(&self) -> impl Iterator<Item = (OptionCode, &[u8])> {
+        self.entries.iter().map(|(c, v)| (*c, v.as_slice()))
+    }
+}
+
+impl Index<OptionCode> for Options {
+    type Output = [u8];
+
+    fn index(&self, code: OptionCode) -> &[u8] {
+        self.get(code).expect("option not present")
+    }
+}
+
+impl FromIterator<(OptionCode, Vec<u8>)> for Options {
+    fn from_iter<I: IntoIterator<Item = (OptionCode, Vec<u8>)>>(iter: I) -> Self {
+        Options {
+            entries: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl IntoIterator for Options {
+    type Item = (OptionCode, Vec<u8>);
+    type IntoIter = std::vec::IntoIter<(OptionCode, Vec<u8>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        // Spot checks against the IANA BOOTP/DHCP parameter registry
+        let known = [
+            (OptionCode::Pad, 0, "Pad"),
+            (OptionCode::SubnetMask, 1, "SubnetMask"),
+            (OptionCode::Router, 3, "Router"),
+            (OptionCode::DomainNameServer, 6, "DomainNameServer"),
+            (OptionCode::HostName, 12, "HostName"),
+            (OptionCode::RequestedIPAddress, 50, "RequestedIPAddress"),
+            (OptionCode::IPAddressLeaseTime, 51, "IPAddressLeaseTime"),
+            (OptionCode::DHCPMessageType, 53, "DHCPMessageType"),
+            (OptionCode::ServerIdentifier, 54, "ServerIdentifier"),
+            (OptionCode::ParameterRequestList, 55, "ParameterRequestList"),
+            (OptionCode::ClientIdentifier, 61, "ClientIdentifier"),
+            (OptionCode::BootFileName, 67, "BootFileName"),
+            (
+                OptionCode::RelayAgentInformation,
+                82,
+                "RelayAgentInformation",
+            ),
+            (OptionCode::IPv6OnlyPreferred, 108, "IPv6OnlyPreferred"),
+            (OptionCode::DomainSearch, 119, "DomainSearch"),
+            (
+                OptionCode::ClasslessRouteFormat,
+                121,
+                "ClasslessRouteFormat",
+            ),
+            (OptionCode::TFTPServerAddress, 150, "TFTPServerAddress"),
+            (OptionCode::End, 255, "End"),
+        ];
+        for (code, value, name) in known {
+            assert_eq!(u8::from(code), value);
+            assert_eq!(OptionCode::try_from(value), Ok(code));
+            assert_eq!(code.to_string(), name);
+            assert_eq!(name.parse::<OptionCode>(), Ok(code));
+        }
+
+        // Unassigned codes have no variant of their own
+        for value in [84, 102, 126, 200, 222, 254] {
+            assert!(OptionCode::try_from(value).is_err());
+            assert_eq!(
+                OptionCode::from_u8(value).to_string(),
+                format!("Other({})", value)
+            );
+        }
+
+        // Every code maps to and from its wire value, and parses back from
+        // both its name and its number
+        for value in 0..=255u8 {
+            let code = OptionCode::from_u8(value);
+            assert_eq!(u8::from(code), value);
+            assert_eq!(code.to_string().parse::<OptionCode>(), Ok(code));
+            assert_eq!(value.to_string().parse::<OptionCode>(), Ok(code));
+        }
+
+        assert!("NoSuchOption".parse::<OptionCode>().is_err());
+        assert!("256".parse::<OptionCode>().is_err());
     }
 }
@@ -0,0 +1,384 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::{OptionCode, OptionError};
+use crate::packet::{HardwareAddr, Packet, MAX_HARDWARE_ADDR_LEN};
+
+/// Type byte of an RFC 4361 identifier (IAID followed by a DUID).
+const DUID_TYPE: u8 = 255;
+
+/// A DHCP Unique Identifier (RFC 8415 section 11).
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Duid {
+    /// DUID-LLT: link-layer address plus the time it was generated.
+    LinkLayerTime {
+        hardware_type: u16,
+        time: u32,
+        addr: Vec<u8>,
+    },
+    /// DUID-EN: assigned by the vendor.
+    Enterprise {
+        enterprise_number: u32,
+        identifier: Vec<u8>,
+    },
+    /// DUID-LL: link-layer address.
+    LinkLayer { hardware_type: u16, addr: Vec<u8> },
+    /// DUID-UUID (RFC 6355).
+    Uuid([u8; 16]),
+    /// Any other DUID type.
+    Other { duid_type: u16, data: Vec<u8> },
+}
+
+impl Duid {
+    /// Returns None for a known DUID type with a malformed body.
+    fn decode(value: &[u8]) -> Option<Self> {
+        if value.len() < 2 {
+            return None;
+        }
+
+        let duid_type = u16::from_be_bytes([value[0], value[1]]);
+        let body = &value[2..];
+
+        match duid_type {
+            1 if body.len() > 6 => Some(Duid::LinkLayerTime {
+                hardware_type: u16::from_be_bytes([body[0], body[1]]),
+                time: u32::from_be_bytes([body[2], body[3], body[4], body[5]]),
+                addr: body[6..].to_vec(),
+            }),
+            2 if body.len() > 4 => Some(Duid::Enterprise {
+                enterprise_number: u32::from_be_bytes([body[0], body[1], body[2], body[3]]),
+                identifier: body[4..].to_vec(),
+            }),
+            3 if body.len() > 2 => Some(Duid::LinkLayer {
+                hardware_type: u16::from_be_bytes([body[0], body[1]]),
+                addr: body[2..].to_vec(),
+            }),
+            4 => {
+                let mut uuid = [0; 16];
+                if body.len() != uuid.len() {
+                    return None;
+                }
+                uuid.copy_from_slice(body);
+                Some(Duid::Uuid(uuid))
+            }
+            1..=4 => None,
+            _ => Some(Duid::Other {
+                duid_type,
+                data: body.to_vec(),
+            }),
+        }
+    }
+
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        match self {
+            Duid::LinkLayerTime {
+                hardware_type,
+                time,
+                addr,
+            } => {
+                out.extend_from_slice(&1u16.to_be_bytes());
+                out.extend_from_slice(&hardware_type.to_be_bytes());
+                out.extend_from_slice(&time.to_be_bytes());
+                out.extend_from_slice(addr);
+            }
+            Duid::Enterprise {
+                enterprise_number,
+                identifier,
+            } => {
+                out.extend_from_slice(&2u16.to_be_bytes());
+                out.extend_from_slice(&enterprise_number.to_be_bytes());
+                out.extend_from_slice(identifier);
+            }
+            Duid::LinkLayer {
+                hardware_type,
+                addr,
+            } => {
+                out.extend_from_slice(&3u16.to_be_bytes());
+                out.extend_from_slice(&hardware_type.to_be_bytes());
+                out.extend_from_slice(addr);
+            }
+            Duid::Uuid(uuid) => {
+                out.extend_from_slice(&4u16.to_be_bytes());
+                out.extend_from_slice(uuid);
+            }
+            Duid::Other { duid_type, data } => {
+                out.extend_from_slice(&duid_type.to_be_bytes());
+                out.extend_from_slice(data);
+            }
+        }
+    }
+}
+
+/// The Client Identifier option (61).
+///
+/// The first byte is a type: an ARP hardware type for the
+/// hardware-type+address form (RFC 2132), or 255 for an IAID and DUID
+/// (RFC 4361). Anything else, including type 0 and malformed DUIDs, is
+/// kept as opaque bytes.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ClientIdentifier {
+    HardwareAddr {
+        htype: u8,
+        addr: HardwareAddr,
+    },
+    Duid {
+        iaid: u32,
+        duid: Duid,
+    },
+    /// The whole option value, type byte included.
+    Opaque(Vec<u8>),
+}
+
+impl ClientIdentifier {
+    pub fn decode(value: &[u8]) -> Result<Self, OptionError> {
+        // RFC 2132 section 9.14: the minimum length is 2
+        if value.len() < 2 {
+            return Err(OptionError::InvalidLength {
+                code: OptionCode::ClientIdentifier,
+                len: value.len(),
+            });
+        }
+
+        let id = match value[0] {
+            DUID_TYPE if value.len() > 5 => {
+                Duid::decode(&value[5..]).map(|duid| ClientIdentifier::Duid {
+                    iaid: u32::from_be_bytes([value[1], value[2], value[3], value[4]]),
+                    duid,
+                })
+            }
+            DUID_TYPE | 0 => None,
+            htype if value.len() - 1 <= MAX_HARDWARE_ADDR_LEN => {
+                Some(ClientIdentifier::HardwareAddr {
+                    htype,
+                    addr: HardwareAddr::from(&value[1..]),
+                })
+            }
+            _ => None,
+        };
+
+        Ok(id.unwrap_or_else(|| ClientIdentifier::Opaque(value.to_vec())))
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            ClientIdentifier::HardwareAddr { htype, addr } => {
+                let mut bytes = vec![*htype];
+                bytes.extend_from_slice(addr.octets());
+                bytes
+            }
+            ClientIdentifier::Duid { iaid, duid } => {
+                let mut bytes = vec![DUID_TYPE];
+                bytes.extend_from_slice(&iaid.to_be_bytes());
+                duid.encode_to(&mut bytes);
+                bytes
+            }
+            ClientIdentifier::Opaque(bytes) => bytes.clone(),
+        }
+    }
+
+    /// The key to look up this client's lease by.
+    ///
+    /// The key is tagged as coming from a client identifier, so it never
+    /// equals the key of a client identified by `chaddr`, even for the
+    /// hardware-type+address form. Decoding and encoding round-trip
+    /// exactly, so the encoded value needs no further normalizing.
+    pub fn key(&self) -> ClientKey {
+        ClientKey::ClientId(self.encode())
+    }
+}
+
+/// Formats the identifier as colon-separated lowercase hex bytes, type byte
+/// first, e.g. `01:aa:bb:cc:dd:ee:ff`.
+impl fmt::Display for ClientIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.encode())
+    }
+}
+
+impl FromStr for ClientIdentifier {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = s
+            .split(':')
+            .map(|byte| u8::from_str_radix(byte, 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| "invalid hex byte")?;
+
+        ClientIdentifier::decode(&bytes).map_err(|_| "client identifier too short")
+    }
+}
+
+/// A key identifying a client, for lease lookups.
+///
+/// Built from option 61 when the client sends it and from `htype` and
+/// `chaddr` otherwise (RFC 2131 section 4.2). The source is part of the
+/// key, so an identifier never collides with a hardware address that has
+/// the same bytes.
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Debug)]
+pub enum ClientKey {
+    /// The Client Identifier option value.
+    ClientId(Vec<u8>),
+    /// `htype` followed by the `chaddr` octets.
+    HardwareAddr(Vec<u8>),
+}
+
+impl ClientKey {
+    /// The key's bytes, without its source.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            ClientKey::ClientId(bytes) | ClientKey::HardwareAddr(bytes) => bytes,
+        }
+    }
+}
+
+/// Formats the key as its source, `id` or `hw`, then colon-separated hex
+/// bytes, e.g. `hw:01:aa:bb:cc:dd:ee:ff`.
+impl fmt::Display for ClientKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientKey::ClientId(bytes) => {
+                f.write_str("id:")?;
+                write_hex(f, bytes)
+            }
+            ClientKey::HardwareAddr(bytes) => {
+                f.write_str("hw:")?;
+                write_hex(f, bytes)
+            }
+        }
+    }
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 {
+            f.write_str(":")?;
+        }
+        write!(f, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
+impl Packet {
+    /// Decode the Client Identifier option, if the packet has one.
+    pub fn client_identifier(&self) -> Option<Result<ClientIdentifier, OptionError>> {
+        self.options
+            .get(OptionCode::ClientIdentifier)
+            .map(ClientIdentifier::decode)
+    }
+
+    /// The key identifying the client: option 61 if present, otherwise
+    /// `htype` followed by `chaddr`.
+    pub fn client_key(&self) -> ClientKey {
+        match self.options.get(OptionCode::ClientIdentifier) {
+            Some(id) if !id.is_empty() => ClientIdentifier::decode(id)
+                .map(|id| id.key())
+                .unwrap_or_else(|_| ClientKey::ClientId(id.to_vec())),
+            _ => {
+                let mut key = vec![self.htype as u8];
+                key.extend_from_slice(self.chaddr.octets());
+                ClientKey::HardwareAddr(key)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_hardware_form() {
+        let id: ClientIdentifier = "01:aa:bb:cc:dd:ee:ff".parse().unwrap();
+        assert_eq!(
+            id,
+            ClientIdentifier::HardwareAddr {
+                htype: 1,
+                addr: HardwareAddr::from([0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff]),
+            }
+        );
+        assert_eq!(id.to_string(), "01:aa:bb:cc:dd:ee:ff");
+        assert_eq!(id.encode(), [1, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff]);
+    }
+
+    #[test]
+    fn test_duid_form() {
+        let value = [
+            255, 0, 0, 0, 7, // IAID
+            0, 1, 0, 1, 0x2a, 0x2b, 0x2c, 0x2d, 1, 2, 3, 4, 5, 6, // DUID-LLT
+        ];
+        let id = ClientIdentifier::decode(&value).unwrap();
+        assert_eq!(
+            id,
+            ClientIdentifier::Duid {
+                iaid: 7,
+                duid: Duid::LinkLayerTime {
+                    hardware_type: 1,
+                    time: 0x2a2b2c2d,
+                    addr: vec![1, 2, 3, 4, 5, 6],
+                },
+            }
+        );
+        assert_eq!(id.encode(), value);
+
+        let mut uuid = vec![255, 1, 2, 3, 4, 0, 4];
+        uuid.extend_from_slice(&[0x11; 16]);
+        assert_eq!(
+            ClientIdentifier::decode(&uuid).unwrap(),
+            ClientIdentifier::Duid {
+                iaid: 0x01020304,
+                duid: Duid::Uuid([0x11; 16]),
+            }
+        );
+
+        // A DUID-UUID with the wrong length is kept opaque
+        let short = &uuid[..uuid.len() - 1];
+        assert_eq!(
+            ClientIdentifier::decode(short).unwrap(),
+            ClientIdentifier::Opaque(short.to_vec())
+        );
+    }
+
+    #[test]
+    fn test_opaque_and_errors() {
+        let id: ClientIdentifier = "00:68:6f:73:74".parse().unwrap();
+        assert_eq!(id, ClientIdentifier::Opaque(b"\0host".to_vec()));
+        assert_eq!(id.to_string(), "00:68:6f:73:74");
+
+        assert!("01".parse::<ClientIdentifier>().is_err());
+        assert!("01:zz".parse::<ClientIdentifier>().is_err());
+    }
+
+    #[test]
+    fn test_client_key() {
+        let mut buf = vec![0; 240];
+        buf[..3].copy_from_slice(&[1, 1, 6]);
+        buf[28..34].copy_from_slice(&[0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff]);
+        buf[236..].copy_from_slice(&crate::packet::DHCP_COOKIE);
+        buf.extend_from_slice(&[53, 1, 1, 255]);
+        let mut packet = Packet::try_from(buf.as_slice()).unwrap();
+
+        let fallback = packet.client_key();
+        assert_eq!(fallback.to_string(), "hw:01:aa:bb:cc:dd:ee:ff");
+
+        // An identifier with the same bytes as htype and chaddr is still a
+        // different client
+        let id: ClientIdentifier = "01:aa:bb:cc:dd:ee:ff".parse().unwrap();
+        packet
+            .options
+            .insert(OptionCode::ClientIdentifier, id.encode());
+        assert_ne!(packet.client_key(), fallback);
+        assert_eq!(packet.client_key(), id.key());
+        assert_eq!(packet.client_key().as_bytes(), fallback.as_bytes());
+        assert_eq!(id.key().to_string(), "id:01:aa:bb:cc:dd:ee:ff");
+
+        packet
+            .options
+            .insert(OptionCode::ClientIdentifier, vec![0, b'x']);
+        assert_eq!(packet.client_key(), ClientKey::ClientId(vec![0, b'x']));
+
+        packet.options.insert(OptionCode::ClientIdentifier, vec![7]);
+        assert_eq!(packet.client_key(), ClientKey::ClientId(vec![7]));
+    }
+}
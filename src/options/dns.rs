@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{OptionCode, OptionError};
+
+/// Longest label allowed in a DNS name.
+const MAX_LABEL_LEN: usize = 63;
+
+/// Longest DNS name in wire form, including length octets and the root.
+const MAX_NAME_LEN: usize = 255;
+
+/// A DNS name as a list of labels, for options that carry names in DNS
+/// wire format.
+///
+/// Parsing from a string checks label and name lengths; a trailing dot is
+/// accepted and dropped. The empty string and "." are the root name.
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Default)]
+pub struct DnsName {
+    labels: Vec<String>,
+}
+
+impl DnsName {
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Length of the name in uncompressed wire form.
+    fn wire_len(labels: &[String]) -> usize {
+        labels.iter().map(|l| l.len() + 1).sum::<usize>() + 1
+    }
+
+    /// Append the name in uncompressed wire form, ending with the root
+    /// label if `fully_qualified`.
+    pub(crate) fn encode_to(&self, out: &mut Vec<u8>, fully_qualified: bool) {
+        for label in &self.labels {
+            out.push(label.len() as u8);
+            out.extend_from_slice(label.as_bytes());
+        }
+        if fully_qualified {
+            out.push(0);
+        }
+    }
+
+    /// Decode an uncompressed name that may omit the root label, as the
+    /// Client FQDN option does for partial names. Returns the name and
+    /// whether it ended with the root label.
+    pub(crate) fn decode_uncompressed(
+        code: OptionCode,
+        value: &[u8],
+    ) -> Result<(Self, bool), OptionError> {
+        let mut labels = Vec::new();
+        let mut pos = 0;
+
+        while pos < value.len() {
+            let len = value[pos] as usize;
+            if len == 0 {
+                // The root label must be the last byte
+                if pos + 1 != value.len() {
+                    return Err(OptionError::InvalidValue { code });
+                }
+                return Ok((DnsName { labels }, true));
+            }
+            if len > MAX_LABEL_LEN {
+                return Err(OptionError::InvalidValue { code });
+            }
+
+            labels.push(read_label(code, value, pos)?);
+            if DnsName::wire_len(&labels) > MAX_NAME_LEN {
+                return Err(OptionError::InvalidValue { code });
+            }
+            pos += 1 + len;
+        }
+
+        Ok((DnsName { labels }, false))
+    }
+
+    /// Encode a list of names using DNS label compression (RFC 1035 section
+    /// 4.1.4), as the Domain Search option requires (RFC 3397).
+    pub fn encode_list(names: &[DnsName]) -> Vec<u8> {
+        let mut out = Vec::new();
+        // Offsets of suffixes already written, keyed by lowercased labels
+        let mut suffixes: HashMap<Vec<String>, usize> = HashMap::new();
+
+        for name in names {
+            let lower: Vec<String> = name.labels.iter().map(|l| l.to_ascii_lowercase()).collect();
+
+            let mut i = 0;
+            while i < lower.len() {
+                if let Some(offset) = suffixes.get(&lower[i..]) {
+                    out.push(0xc0 | (offset >> 8) as u8);
+                    out.push(*offset as u8);
+                    break;
+                }
+
+                // Pointers only have 14 bits of offset
+                if out.len() < 0x4000 {
+                    suffixes.insert(lower[i..].to_vec(), out.len());
+                }
+
+                out.push(name.labels[i].len() as u8);
+                out.extend_from_slice(name.labels[i].as_bytes());
+                i += 1;
+            }
+
+            if i == lower.len() {
+                out.push(0);
+            }
+        }
+
+        out
+    }
+
+    /// Decode a compressed list of names. Compression pointers must point
+    /// strictly before the previous jump, which rules out loops.
+    pub fn decode_list(code: OptionCode, value: &[u8]) -> Result<Vec<DnsName>, OptionError> {
+        let mut names = Vec::new();
+        let mut pos = 0;
+
+        if value.is_empty() {
+            return Err(OptionError::InvalidLength { code, len: 0 });
+        }
+
+        while pos < value.len() {
+            let (name, next) = read_name(code, value, pos)?;
+            names.push(name);
+            pos = next;
+        }
+
+        Ok(names)
+    }
+}
+
+/// Read a possibly compressed name starting at `start`, returning it and
+/// the offset just past it.
+fn read_name(code: OptionCode, buf: &[u8], start: usize) -> Result<(DnsName, usize), OptionError> {
+    let truncated = OptionError::InvalidLength {
+        code,
+        len: buf.len(),
+    };
+    let mut labels = Vec::new();
+    let mut pos = start;
+    let mut limit = start;
+    let mut end = None;
+
+    loop {
+        let len = *buf.get(pos).ok_or_else(|| truncated.clone())? as usize;
+
+        match len {
+            0 => {
+                let end = end.unwrap_or(pos + 1);
+                return Ok((DnsName { labels }, end));
+            }
+            l if l & 0xc0 == 0xc0 => {
+                let low = *buf.get(pos + 1).ok_or_else(|| truncated.clone())? as usize;
+                let target = ((l & 0x3f) << 8) | low;
+                if target >= limit {
+                    return Err(OptionError::InvalidValue { code });
+                }
+
+                end.get_or_insert(pos + 2);
+                limit = target;
+                pos = target;
+            }
+            l if l > MAX_LABEL_LEN => return Err(OptionError::InvalidValue { code }),
+            l => {
+                labels.push(read_label(code, buf, pos)?);
+                if DnsName::wire_len(&labels) > MAX_NAME_LEN {
+                    return Err(OptionError::InvalidValue { code });
+                }
+                pos += 1 + l;
+            }
+        }
+    }
+}
+
+/// Read the label whose length octet is at `pos`.
+fn read_label(code: OptionCode, buf: &[u8], pos: usize) -> Result<String, OptionError> {
+    let len = buf[pos] as usize;
+    let bytes = buf
+        .get(pos + 1..pos + 1 + len)
+        .ok_or(OptionError::InvalidLength {
+            code,
+            len: buf.len(),
+        })?;
+
+    String::from_utf8(bytes.to_vec()).map_err(|_| OptionError::InvalidValue { code })
+}
+
+impl FromStr for DnsName {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.strip_suffix('.').unwrap_or(value);
+        if value.is_empty() {
+            return Ok(DnsName::default());
+        }
+
+        let labels: Vec<String> = value.split('.').map(str::to_owned).collect();
+
+        if labels.iter().any(|l| l.is_empty()) {
+            return Err("empty label in DNS name");
+        }
+        if labels.iter().any(|l| l.len() > MAX_LABEL_LEN) {
+            return Err("DNS label longer than 63 bytes");
+        }
+        if DnsName::wire_len(&labels) > MAX_NAME_LEN {
+            return Err("DNS name longer than 255 bytes");
+        }
+
+        Ok(DnsName { labels })
+    }
+}
+
+impl fmt::Display for DnsName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.labels.join("."))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::options::{DhcpOption, Options};
+    use crate::packet::Packet;
+    use crate::view::PacketView;
+    use std::convert::TryFrom;
+
+    fn names(list: &[&str]) -> Vec<DnsName> {
+        list.iter().map(|n| n.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_rfc3397_example() {
+        let list = names(&["eng.apple.com", "marketing.apple.com"]);
+        let bytes = [
+            3, b'e', b'n', b'g', 5, b'a', b'p', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0, 9, b'm',
+            b'a', b'r', b'k', b'e', b't', b'i', b'n', b'g', 0xc0, 0x04,
+        ];
+
+        assert_eq!(DnsName::encode_list(&list), bytes);
+        assert_eq!(
+            DnsName::decode_list(OptionCode::DomainSearch, &bytes).unwrap(),
+            list
+        );
+    }
+
+    #[test]
+    fn test_compression_is_case_insensitive() {
+        let list = names(&["Example.COM", "example.com.", "a.example.com"]);
+        let bytes = DnsName::encode_list(&list);
+        assert_eq!(bytes.len(), 13 + 2 + 4);
+        assert_eq!(
+            DnsName::decode_list(OptionCode::DomainSearch, &bytes).unwrap()[2].to_string(),
+            "a.Example.COM"
+        );
+    }
+
+    #[test]
+    fn test_pointer_loops_rejected() {
+        let code = OptionCode::DomainSearch;
+
+        // Points at itself
+        assert_eq!(
+            DnsName::decode_list(code, &[0xc0, 0x00]),
+            Err(OptionError::InvalidValue { code })
+        );
+        // Two names pointing at each other
+        assert_eq!(
+            DnsName::decode_list(code, &[1, b'a', 0xc0, 0x04, 1, b'b', 0xc0, 0x00]),
+            Err(OptionError::InvalidValue { code })
+        );
+        // Pointer past the end
+        assert_eq!(
+            DnsName::decode_list(code, &[1, b'a', 0xc0]),
+            Err(OptionError::InvalidLength { code, len: 3 })
+        );
+        assert_eq!(
+            DnsName::decode_list(code, &[3, b'a', b'b']),
+            Err(OptionError::InvalidLength { code, len: 3 })
+        );
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        assert!("a..b".parse::<DnsName>().is_err());
+        assert!("x".repeat(64).parse::<DnsName>().is_err());
+        assert!(vec!["x".repeat(63); 4]
+            .join(".")
+            .parse::<DnsName>()
+            .is_err());
+        assert!("".parse::<DnsName>().unwrap().is_root());
+    }
+
+    #[test]
+    fn test_long_list_split_across_instances() {
+        let list: Vec<DnsName> = (0..30)
+            .map(|i| {
+                format!("site{}.region{}.corp.example.net", i, i % 3)
+                    .parse()
+                    .unwrap()
+            })
+            .collect();
+
+        let mut buf = vec![0; 240];
+        buf[..3].copy_from_slice(&[2, 1, 6]);
+        buf[236..].copy_from_slice(&crate::packet::DHCP_COOKIE);
+        let mut p = Packet::try_from(buf.as_slice()).unwrap();
+        p.options = Options::new();
+        p.options
+            .insert_decoded(DhcpOption::DomainSearch(list.clone()))
+            .unwrap();
+        assert!(p.options[OptionCode::DomainSearch].len() > 255);
+
+        let bytes = Vec::from(&p);
+        let instances = PacketView::new(&bytes)
+            .unwrap()
+            .options()
+            .filter(|(c, _)| *c == OptionCode::DomainSearch)
+            .count();
+        assert!(instances >= 2);
+
+        let parsed = Packet::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(
+            parsed.options.get_decoded(OptionCode::DomainSearch),
+            Some(Ok(DhcpOption::DomainSearch(list)))
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use super::{DnsName, OptionCode, OptionError};
+use crate::packet::Packet;
+
+const FLAG_S: u8 = 0x01;
+const FLAG_O: u8 = 0x02;
+const FLAG_E: u8 = 0x04;
+const FLAG_N: u8 = 0x08;
+
+/// The Client FQDN option (81, RFC 4702), used by the client and server to
+/// agree on who updates DNS for the client's name.
+///
+/// In a request, `server_updates` asks the server to update the A record
+/// and `no_server_updates` asks it to update nothing. In a reply they say
+/// what the server will do, with `overridden` set when the server went
+/// against the client's wishes.
+#[derive(PartialEq, Clone, Debug)]
+pub struct ClientFqdn {
+    /// S flag: the server updates (or should update) the A record.
+    pub server_updates: bool,
+    /// O flag: the server has overridden the client's S flag.
+    pub overridden: bool,
+    /// E flag: the name uses canonical DNS wire format rather than the
+    /// deprecated ASCII form.
+    pub canonical: bool,
+    /// N flag: the server performs (or should perform) no updates.
+    pub no_server_updates: bool,
+    /// Deprecated; sent as 0 by clients and 255 by servers.
+    pub rcode1: u8,
+    /// Deprecated; sent as 0 by clients and 255 by servers.
+    pub rcode2: u8,
+    pub name: DnsName,
+    /// False for a partial name that the server should complete.
+    pub fully_qualified: bool,
+}
+
+impl ClientFqdn {
+    pub fn decode(value: &[u8]) -> Result<Self, OptionError> {
+        let code = OptionCode::ClientFQDN;
+
+        if value.len() < 3 {
+            return Err(OptionError::InvalidLength {
+                code,
+                len: value.len(),
+            });
+        }
+
+        let flags = value[0];
+        // The high bits must be zero and S and N cannot both be set
+        if flags & 0xf0 != 0 || (flags & FLAG_S != 0 && flags & FLAG_N != 0) {
+            return Err(OptionError::InvalidValue { code });
+        }
+
+        let canonical = flags & FLAG_E != 0;
+        let (name, fully_qualified) = if canonical {
+            DnsName::decode_uncompressed(code, &value[3..])?
+        } else {
+            let ascii = std::str::from_utf8(&value[3..])
+                .map_err(|_| OptionError::InvalidValue { code })?
+                .trim_end_matches('\0');
+            let name = ascii
+                .parse()
+                .map_err(|_| OptionError::InvalidValue { code })?;
+            (name, ascii.ends_with('.'))
+        };
+
+        Ok(ClientFqdn {
+            server_updates: flags & FLAG_S != 0,
+            overridden: flags & FLAG_O != 0,
+            canonical,
+            no_server_updates: flags & FLAG_N != 0,
+            rcode1: value[1],
+            rcode2: value[2],
+            name,
+            fully_qualified,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut flags = 0;
+        if self.server_updates {
+            flags |= FLAG_S;
+        }
+        if self.overridden {
+            flags |= FLAG_O;
+        }
+        if self.canonical {
+            flags |= FLAG_E;
+        }
+        if self.no_server_updates {
+            flags |= FLAG_N;
+        }
+
+        let mut bytes = vec![flags, self.rcode1, self.rcode2];
+
+        if self.canonical {
+            self.name.encode_to(&mut bytes, self.fully_qualified);
+        } else {
+            bytes.extend_from_slice(self.name.to_string().as_bytes());
+            if self.fully_qualified && !self.name.is_root() {
+                bytes.push(b'.');
+            }
+        }
+
+        bytes
+    }
+
+    /// Build the server's reply to this client request.
+    ///
+    /// `update_a` and `update_ptr` say which records the server will
+    /// update. The reply sets S to `update_a`, sets O if that differs from
+    /// what the client asked for, sets N if the server updates nothing, and
+    /// keeps the client's name encoding as RFC 4702 section 4 requires. The
+    /// name is the client's; replace it if the server chose another.
+    pub fn reply(&self, update_a: bool, update_ptr: bool) -> ClientFqdn {
+        ClientFqdn {
+            server_updates: update_a,
+            overridden: update_a != self.server_updates,
+            canonical: self.canonical,
+            no_server_updates: !update_a && !update_ptr,
+            rcode1: 255,
+            rcode2: 255,
+            name: self.name.clone(),
+            fully_qualified: self.fully_qualified,
+        }
+    }
+}
+
+impl Packet {
+    /// Decode the Client FQDN option, if the packet has one.
+    pub fn client_fqdn(&self) -> Option<Result<ClientFqdn, OptionError>> {
+        self.options
+            .get(OptionCode::ClientFQDN)
+            .map(ClientFqdn::decode)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_canonical_encoding() {
+        let value = [
+            0x05, 0, 0, // E and S set
+            6, b'l', b'a', b'p', b't', b'o', b'p', 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3,
+            b'c', b'o', b'm', 0,
+        ];
+
+        let fqdn = ClientFqdn::decode(&value).unwrap();
+        assert!(fqdn.server_updates);
+        assert!(fqdn.canonical);
+        assert!(!fqdn.overridden);
+        assert!(!fqdn.no_server_updates);
+        assert!(fqdn.fully_qualified);
+        assert_eq!(fqdn.name.to_string(), "laptop.example.com");
+        assert_eq!(fqdn.encode(), value);
+
+        // A partial name leaves off the root label
+        let partial = [0x04, 0, 0, 6, b'l', b'a', b'p', b't', b'o', b'p'];
+        let fqdn = ClientFqdn::decode(&partial).unwrap();
+        assert!(!fqdn.fully_qualified);
+        assert_eq!(fqdn.name.to_string(), "laptop");
+        assert_eq!(fqdn.encode(), partial);
+    }
+
+    #[test]
+    fn test_ascii_encoding() {
+        let value = b"\x01\x00\x00laptop.example.com.";
+        let fqdn = ClientFqdn::decode(value).unwrap();
+        assert!(!fqdn.canonical);
+        assert!(fqdn.fully_qualified);
+        assert_eq!(fqdn.name.to_string(), "laptop.example.com");
+        assert_eq!(fqdn.encode(), value.to_vec());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let code = OptionCode::ClientFQDN;
+        assert_eq!(
+            ClientFqdn::decode(&[0x04, 0]),
+            Err(OptionError::InvalidLength { code, len: 2 })
+        );
+        // S and N together
+        assert_eq!(
+            ClientFqdn::decode(&[0x09, 0, 0]),
+            Err(OptionError::InvalidValue { code })
+        );
+        // Root label in the middle of the name
+        assert_eq!(
+            ClientFqdn::decode(&[0x04, 0, 0, 1, b'a', 0, 1, b'b']),
+            Err(OptionError::InvalidValue { code })
+        );
+    }
+
+    #[test]
+    fn test_reply_flags() {
+        let request = ClientFqdn::decode(&[0x04, 0, 0, 1, b'a', 0]).unwrap();
+
+        // Client asked to do the A update itself, server does it anyway
+        let reply = request.reply(true, true);
+        assert_eq!(reply.encode(), [0x07, 255, 255, 1, b'a', 0]);
+
+        // Server agrees and only updates the PTR record
+        let reply = request.reply(false, true);
+        assert_eq!(reply.encode()[0], 0x04);
+
+        // Server does no updates at all
+        let reply = request.reply(false, false);
+        assert_eq!(reply.encode()[0], 0x0c);
+    }
+}
@@ -0,0 +1,389 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::{OptionCode, OptionError};
+use crate::packet::Packet;
+
+/// Declares `ProcessorArchitecture` from the IANA "Processor Architecture
+/// Types" registry along with its conversions to and from `u16`.
+macro_rules! processor_architectures {
+    ($($(#[$doc:meta])* $name:ident = $value:expr,)*) => {
+        /// A client system architecture type (option 93, RFC 4578), from the
+        /// IANA Processor Architecture Types registry.
+        #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+        pub enum ProcessorArchitecture {
+            $($(#[$doc])* $name,)*
+            /// A type not listed above.
+            Other(u16),
+        }
+
+        impl From<u16> for ProcessorArchitecture {
+            fn from(value: u16) -> Self {
+                match value {
+                    $($value => ProcessorArchitecture::$name,)*
+                    _ => ProcessorArchitecture::Other(value),
+                }
+            }
+        }
+
+        impl From<ProcessorArchitecture> for u16 {
+            fn from(arch: ProcessorArchitecture) -> u16 {
+                match arch {
+                    $(ProcessorArchitecture::$name => $value,)*
+                    ProcessorArchitecture::Other(value) => value,
+                }
+            }
+        }
+    };
+}
+
+processor_architectures! {
+    /// x86 BIOS (legacy PXE).
+    X86Bios = 0,
+    NecPc98 = 1,
+    /// EFI on Itanium.
+    ItaniumUefi = 2,
+    DecAlpha = 3,
+    ArcX86 = 4,
+    IntelLeanClient = 5,
+    X86Uefi = 6,
+    X64Uefi = 7,
+    EfiXscale = 8,
+    EfiByteCode = 9,
+    Arm32Uefi = 10,
+    Arm64Uefi = 11,
+    PowerPcOpenFirmware = 12,
+    PowerPcEpapr = 13,
+    PowerOpalV3 = 14,
+    X86UefiHttp = 15,
+    X64UefiHttp = 16,
+    EfiByteCodeHttp = 17,
+    Arm32UefiHttp = 18,
+    Arm64UefiHttp = 19,
+    /// PC/AT BIOS booting over HTTP.
+    X86BiosHttp = 20,
+    Arm32Uboot = 21,
+    Arm64Uboot = 22,
+    Arm32UbootHttp = 23,
+    Arm64UbootHttp = 24,
+    RiscV32Uefi = 25,
+    RiscV32UefiHttp = 26,
+    RiscV64Uefi = 27,
+    RiscV64UefiHttp = 28,
+    RiscV128Uefi = 29,
+    RiscV128UefiHttp = 30,
+    S390Basic = 31,
+    S390Extended = 32,
+    Mips32Uefi = 33,
+    Mips64Uefi = 34,
+    Sunway32Uefi = 35,
+    Sunway64Uefi = 36,
+    LoongArch32Uefi = 37,
+    LoongArch32UefiHttp = 38,
+    LoongArch64Uefi = 39,
+    LoongArch64UefiHttp = 40,
+    ArmRpiboot = 41,
+}
+
+impl ProcessorArchitecture {
+    /// Whether the client boots over HTTP rather than TFTP, and so expects
+    /// a URL as its boot file.
+    pub fn is_http_boot(&self) -> bool {
+        use self::ProcessorArchitecture::*;
+
+        matches!(
+            self,
+            X86UefiHttp
+                | X64UefiHttp
+                | EfiByteCodeHttp
+                | Arm32UefiHttp
+                | Arm64UefiHttp
+                | X86BiosHttp
+                | Arm32UbootHttp
+                | Arm64UbootHttp
+                | RiscV32UefiHttp
+                | RiscV64UefiHttp
+                | RiscV128UefiHttp
+                | LoongArch32UefiHttp
+                | LoongArch64UefiHttp
+        )
+    }
+
+    /// Whether the client runs UEFI firmware.
+    pub fn is_uefi(&self) -> bool {
+        use self::ProcessorArchitecture::*;
+
+        matches!(
+            self,
+            ItaniumUefi
+                | X86Uefi
+                | X64Uefi
+                | EfiXscale
+                | EfiByteCode
+                | Arm32Uefi
+                | Arm64Uefi
+                | X86UefiHttp
+                | X64UefiHttp
+                | EfiByteCodeHttp
+                | Arm32UefiHttp
+                | Arm64UefiHttp
+                | RiscV32Uefi
+                | RiscV32UefiHttp
+                | RiscV64Uefi
+                | RiscV64UefiHttp
+                | RiscV128Uefi
+                | RiscV128UefiHttp
+                | Mips32Uefi
+                | Mips64Uefi
+                | Sunway32Uefi
+                | Sunway64Uefi
+                | LoongArch32Uefi
+                | LoongArch32UefiHttp
+                | LoongArch64Uefi
+                | LoongArch64UefiHttp
+        )
+    }
+
+    pub fn decode_list(value: &[u8]) -> Result<Vec<Self>, OptionError> {
+        if value.is_empty() || value.len() % 2 != 0 {
+            return Err(OptionError::InvalidLength {
+                code: OptionCode::ClientArchitecture,
+                len: value.len(),
+            });
+        }
+
+        Ok(value
+            .chunks(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]).into())
+            .collect())
+    }
+
+    pub fn encode_list(list: &[Self]) -> Vec<u8> {
+        list.iter()
+            .flat_map(|arch| u16::from(*arch).to_be_bytes())
+            .collect()
+    }
+}
+
+/// The Client Network Interface Identifier option (94, RFC 4578): the
+/// version of the network interface the client boots with.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct ClientNetworkInterface {
+    /// 1 for UNDI (Universal Network Device Interface).
+    pub interface_type: u8,
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl ClientNetworkInterface {
+    pub fn decode(value: &[u8]) -> Result<Self, OptionError> {
+        match value {
+            [interface_type, major, minor] => Ok(ClientNetworkInterface {
+                interface_type: *interface_type,
+                major: *major,
+                minor: *minor,
+            }),
+            _ => Err(OptionError::InvalidLength {
+                code: OptionCode::ClientNetworkInterfaceIdentifier,
+                len: value.len(),
+            }),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        vec![self.interface_type, self.major, self.minor]
+    }
+}
+
+/// A 128-bit UUID, as carried in the Client Machine Identifier option (97,
+/// RFC 4578).
+///
+/// The bytes are kept in wire order. Many PXE clients send their SMBIOS
+/// UUID, whose first three fields are little-endian, so the formatted form
+/// may not match what the firmware setup screen shows.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct Uuid([u8; 16]);
+
+impl Uuid {
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        Uuid(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    /// Decode the value of option 97: a type byte of 0 followed by the
+    /// UUID.
+    pub fn decode(value: &[u8]) -> Result<Self, OptionError> {
+        let code = OptionCode::ClientMachineIdentifier;
+
+        if value.len() != 17 {
+            return Err(OptionError::InvalidLength {
+                code,
+                len: value.len(),
+            });
+        }
+        if value[0] != 0 {
+            return Err(OptionError::InvalidValue { code });
+        }
+
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&value[1..]);
+        Ok(Uuid(bytes))
+    }
+
+    /// Encode as the value of option 97.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![0];
+        bytes.extend_from_slice(&self.0);
+        bytes
+    }
+}
+
+/// Formats the UUID as `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`.
+impl fmt::Display for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if let 4 | 6 | 8 | 10 = i {
+                f.write_str("-")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Uuid {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let groups: Vec<&str> = s.split('-').collect();
+        let lengths: Vec<usize> = groups.iter().map(|g| g.len()).collect();
+        if lengths != [8, 4, 4, 4, 12] {
+            return Err("UUID must have the form xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx");
+        }
+
+        let hex = groups.concat();
+        let mut bytes = [0; 16];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = hex
+                .get(2 * i..2 * i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .ok_or("invalid hex digit in UUID")?;
+        }
+
+        Ok(Uuid(bytes))
+    }
+}
+
+impl Packet {
+    /// Decode the client's architecture types (option 93), in the client's
+    /// order of preference.
+    pub fn client_architectures(&self) -> Option<Result<Vec<ProcessorArchitecture>, OptionError>> {
+        self.options
+            .get(OptionCode::ClientArchitecture)
+            .map(ProcessorArchitecture::decode_list)
+    }
+
+    /// Decode the Client Network Interface Identifier option (94).
+    pub fn client_network_interface(&self) -> Option<Result<ClientNetworkInterface, OptionError>> {
+        self.options
+            .get(OptionCode::ClientNetworkInterfaceIdentifier)
+            .map(ClientNetworkInterface::decode)
+    }
+
+    /// Decode the Client Machine Identifier option (97).
+    pub fn client_machine_id(&self) -> Option<Result<Uuid, OptionError>> {
+        self.options
+            .get(OptionCode::ClientMachineIdentifier)
+            .map(Uuid::decode)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_architectures() {
+        let list = ProcessorArchitecture::decode_list(&[0, 7, 0, 16, 0x12, 0x34]).unwrap();
+        assert_eq!(
+            list,
+            vec![
+                ProcessorArchitecture::X64Uefi,
+                ProcessorArchitecture::X64UefiHttp,
+                ProcessorArchitecture::Other(0x1234),
+            ]
+        );
+        assert_eq!(
+            ProcessorArchitecture::encode_list(&list),
+            [0, 7, 0, 16, 0x12, 0x34]
+        );
+
+        assert!(!ProcessorArchitecture::X86Bios.is_uefi());
+        assert!(ProcessorArchitecture::Arm64Uefi.is_uefi());
+        assert!(ProcessorArchitecture::from(2).is_uefi());
+        assert!(!ProcessorArchitecture::from(1).is_uefi());
+        assert!(!ProcessorArchitecture::Arm64Uefi.is_http_boot());
+        assert!(ProcessorArchitecture::X86BiosHttp.is_http_boot());
+        assert_eq!(u16::from(ProcessorArchitecture::ArmRpiboot), 41);
+
+        assert!(ProcessorArchitecture::decode_list(&[0]).is_err());
+        assert!(ProcessorArchitecture::decode_list(&[]).is_err());
+    }
+
+    #[test]
+    fn test_uuid() {
+        let uuid: Uuid = "4c4c4544-0042-3510-8052-b4c04f4e4d32".parse().unwrap();
+        assert_eq!(uuid.to_string(), "4c4c4544-0042-3510-8052-b4c04f4e4d32");
+        assert_eq!(uuid.as_bytes()[..4], [0x4c, 0x4c, 0x45, 0x44]);
+
+        let value = uuid.encode();
+        assert_eq!(value.len(), 17);
+        assert_eq!(Uuid::decode(&value), Ok(uuid));
+
+        let mut bad_type = value.clone();
+        bad_type[0] = 1;
+        assert_eq!(
+            Uuid::decode(&bad_type),
+            Err(OptionError::InvalidValue {
+                code: OptionCode::ClientMachineIdentifier
+            })
+        );
+
+        assert!("4c4c4544-0042-3510-8052".parse::<Uuid>().is_err());
+        assert!("4c4c4544-0042-3510-8052-b4c04f4e4dzz"
+            .parse::<Uuid>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_packet_helpers() {
+        let mut buf = vec![0; 240];
+        buf[..3].copy_from_slice(&[1, 1, 6]);
+        buf[236..].copy_from_slice(&crate::packet::DHCP_COOKIE);
+        buf.extend_from_slice(&[53, 1, 1, 93, 2, 0, 11, 94, 3, 1, 3, 16, 97, 17, 0]);
+        buf.extend_from_slice(&[0xab; 16]);
+        buf.push(255);
+
+        let packet = Packet::try_from(buf.as_slice()).unwrap();
+        assert_eq!(
+            packet.client_architectures(),
+            Some(Ok(vec![ProcessorArchitecture::Arm64Uefi]))
+        );
+        assert_eq!(
+            packet.client_network_interface(),
+            Some(Ok(ClientNetworkInterface {
+                interface_type: 1,
+                major: 3,
+                minor: 16,
+            }))
+        );
+        assert_eq!(
+            packet.client_machine_id(),
+            Some(Ok(Uuid::from_bytes([0xab; 16])))
+        );
+    }
+}
@@ -0,0 +1,330 @@
+use std::net::Ipv4Addr;
+
+use super::{OptionCode, OptionError};
+use crate::packet::Packet;
+
+/// A sub-option of the Relay Agent Information option (82).
+#[derive(PartialEq, Clone, Debug)]
+pub enum RelaySubOption {
+    /// Agent Circuit ID (1), typically the port the request arrived on.
+    CircuitId(Vec<u8>),
+    /// Agent Remote ID (2), identifying the remote end of the circuit.
+    RemoteId(Vec<u8>),
+    /// Link Selection (5, RFC 3527), the subnet to allocate from.
+    LinkSelection(Ipv4Addr),
+    /// Subscriber ID (6, RFC 3993).
+    SubscriberId(Vec<u8>),
+    /// Relay Agent Flags (10, RFC 5010). The high bit is set when the
+    /// relay received the request by unicast.
+    Flags(u8),
+    /// Server Identifier Override (11, RFC 5107).
+    ServerIdentifierOverride(Ipv4Addr),
+    /// Virtual Subnet Selection (151, RFC 6607): a type octet then data.
+    VirtualSubnetSelection(Vec<u8>),
+    /// Any other sub-option, as code and raw value.
+    Other(u8, Vec<u8>),
+}
+
+impl RelaySubOption {
+    pub fn code(&self) -> u8 {
+        match self {
+            RelaySubOption::CircuitId(_) => 1,
+            RelaySubOption::RemoteId(_) => 2,
+            RelaySubOption::LinkSelection(_) => 5,
+            RelaySubOption::SubscriberId(_) => 6,
+            RelaySubOption::Flags(_) => 10,
+            RelaySubOption::ServerIdentifierOverride(_) => 11,
+            RelaySubOption::VirtualSubnetSelection(_) => 151,
+            RelaySubOption::Other(code, _) => *code,
+        }
+    }
+
+    fn value(&self) -> Vec<u8> {
+        match self {
+            RelaySubOption::CircuitId(v)
+            | RelaySubOption::RemoteId(v)
+            | RelaySubOption::SubscriberId(v)
+            | RelaySubOption::VirtualSubnetSelection(v)
+            | RelaySubOption::Other(_, v) => v.clone(),
+            RelaySubOption::LinkSelection(addr)
+            | RelaySubOption::ServerIdentifierOverride(addr) => addr.octets().to_vec(),
+            RelaySubOption::Flags(flags) => vec![*flags],
+        }
+    }
+
+    fn decode(code: u8, value: &[u8]) -> Result<Self, OptionError> {
+        let invalid = OptionError::InvalidValue {
+            code: OptionCode::RelayAgentInformation,
+        };
+        let addr = |v: &[u8]| match v {
+            [a, b, c, d] => Ok(Ipv4Addr::new(*a, *b, *c, *d)),
+            _ => Err(invalid.clone()),
+        };
+
+        Ok(match code {
+            1 => RelaySubOption::CircuitId(value.to_vec()),
+            2 => RelaySubOption::RemoteId(value.to_vec()),
+            5 => RelaySubOption::LinkSelection(addr(value)?),
+            6 => RelaySubOption::SubscriberId(value.to_vec()),
+            10 => match value {
+                [flags] => RelaySubOption::Flags(*flags),
+                _ => return Err(invalid),
+            },
+            11 => RelaySubOption::ServerIdentifierOverride(addr(value)?),
+            151 => RelaySubOption::VirtualSubnetSelection(value.to_vec()),
+            _ => RelaySubOption::Other(code, value.to_vec()),
+        })
+    }
+}
+
+/// The Relay Agent Information option (82, RFC 3046) as a list of
+/// sub-options in wire order.
+///
+/// A server must echo the option back unchanged in its reply; see
+/// `Packet::echo_relay_agent_information`.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct RelayAgentInformation {
+    pub sub_options: Vec<RelaySubOption>,
+}
+
+impl RelayAgentInformation {
+    pub fn new() -> Self {
+        RelayAgentInformation::default()
+    }
+
+    /// Append a sub-option.
+    pub fn with(mut self, sub_option: RelaySubOption) -> Self {
+        self.sub_options.push(sub_option);
+        self
+    }
+
+    pub fn decode(value: &[u8]) -> Result<Self, OptionError> {
+        let mut sub_options = Vec::new();
+        let mut rest = value;
+
+        while !rest.is_empty() {
+            if rest.len() < 2 || rest.len() < 2 + rest[1] as usize {
+                return Err(OptionError::InvalidLength {
+                    code: OptionCode::RelayAgentInformation,
+                    len: value.len(),
+                });
+            }
+
+            let size = rest[1] as usize;
+            sub_options.push(RelaySubOption::decode(rest[0], &rest[2..2 + size])?);
+            rest = &rest[2 + size..];
+        }
+
+        if sub_options.is_empty() {
+            return Err(OptionError::InvalidLength {
+                code: OptionCode::RelayAgentInformation,
+                len: 0,
+            });
+        }
+
+        Ok(RelayAgentInformation { sub_options })
+    }
+
+    /// Fails if a sub-option value is longer than 255 bytes.
+    pub fn encode(&self) -> Result<Vec<u8>, OptionError> {
+        let mut bytes = Vec::new();
+
+        for sub in &self.sub_options {
+            let value = sub.value();
+            if value.len() > 255 {
+                return Err(OptionError::InvalidLength {
+                    code: OptionCode::RelayAgentInformation,
+                    len: value.len(),
+                });
+            }
+            bytes.push(sub.code());
+            bytes.push(value.len() as u8);
+            bytes.extend_from_slice(&value);
+        }
+
+        Ok(bytes)
+    }
+
+    /// The first sub-option with `code`.
+    pub fn get(&self, code: u8) -> Option<&RelaySubOption> {
+        self.sub_options.iter().find(|s| s.code() == code)
+    }
+
+    pub fn circuit_id(&self) -> Option<&[u8]> {
+        match self.get(1) {
+            Some(RelaySubOption::CircuitId(v)) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn remote_id(&self) -> Option<&[u8]> {
+        match self.get(2) {
+            Some(RelaySubOption::RemoteId(v)) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn link_selection(&self) -> Option<Ipv4Addr> {
+        match self.get(5) {
+            Some(RelaySubOption::LinkSelection(addr)) => Some(*addr),
+            _ => None,
+        }
+    }
+
+    pub fn subscriber_id(&self) -> Option<&[u8]> {
+        match self.get(6) {
+            Some(RelaySubOption::SubscriberId(v)) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn flags(&self) -> Option<u8> {
+        match self.get(10) {
+            Some(RelaySubOption::Flags(flags)) => Some(*flags),
+            _ => None,
+        }
+    }
+
+    pub fn server_identifier_override(&self) -> Option<Ipv4Addr> {
+        match self.get(11) {
+            Some(RelaySubOption::ServerIdentifierOverride(addr)) => Some(*addr),
+            _ => None,
+        }
+    }
+
+    pub fn virtual_subnet_selection(&self) -> Option<&[u8]> {
+        match self.get(151) {
+            Some(RelaySubOption::VirtualSubnetSelection(v)) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl Packet {
+    /// Decode the Relay Agent Information option, if the packet has one.
+    pub fn relay_agent_information(&self) -> Option<Result<RelayAgentInformation, OptionError>> {
+        self.options
+            .get(OptionCode::RelayAgentInformation)
+            .map(RelayAgentInformation::decode)
+    }
+
+    /// Copy the request's Relay Agent Information option into this reply
+    /// byte for byte, as RFC 3046 requires, placing it last.
+    ///
+    /// The value is not decoded and re-encoded, so this cannot fail and
+    /// cannot alter what the relay sent.
+    pub fn echo_relay_agent_information(&mut self, request: &Packet) {
+        self.options.remove(OptionCode::RelayAgentInformation);
+        if let Some(info) = request.options.get(OptionCode::RelayAgentInformation) {
+            self.options
+                .insert(OptionCode::RelayAgentInformation, info.to_vec());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+
+    use crate::options::DhcpOption;
+
+    const OPTION_82: [u8; 22] = [
+        1, 6, 0, 4, 0, 1, 0, 5, // Circuit-ID: VLAN 1, port 5
+        2, 4, 0xc0, 0xa8, 0, 1, // Remote-ID
+        5, 4, 10, 1, 2, 0, // Link Selection
+        10, 1, // Flags, cut short
+    ];
+
+    #[test]
+    fn test_decode_relay_info() {
+        assert_eq!(
+            RelayAgentInformation::decode(&OPTION_82),
+            Err(OptionError::InvalidLength {
+                code: OptionCode::RelayAgentInformation,
+                len: 22
+            })
+        );
+
+        let mut value = OPTION_82.to_vec();
+        value.push(0x80);
+        value.extend_from_slice(&[200, 1, 9]);
+
+        let info = RelayAgentInformation::decode(&value).unwrap();
+        assert_eq!(info.circuit_id(), Some(&[0, 4, 0, 1, 0, 5][..]));
+        assert_eq!(info.remote_id(), Some(&[0xc0, 0xa8, 0, 1][..]));
+        assert_eq!(info.link_selection(), Some(Ipv4Addr::new(10, 1, 2, 0)));
+        assert_eq!(info.flags(), Some(0x80));
+        assert_eq!(info.subscriber_id(), None);
+        assert_eq!(info.get(200), Some(&RelaySubOption::Other(200, vec![9])));
+        assert_eq!(info.encode(), Ok(value));
+
+        assert_eq!(
+            RelayAgentInformation::decode(&[5, 3, 10, 1, 2]),
+            Err(OptionError::InvalidValue {
+                code: OptionCode::RelayAgentInformation
+            })
+        );
+    }
+
+    #[test]
+    fn test_encode_too_long() {
+        let info = RelayAgentInformation::new()
+            .with(RelaySubOption::CircuitId(b"eth0".to_vec()))
+            .with(RelaySubOption::RemoteId(vec![7; 256]));
+        let error = OptionError::InvalidLength {
+            code: OptionCode::RelayAgentInformation,
+            len: 256,
+        };
+        assert_eq!(info.encode(), Err(error.clone()));
+        assert_eq!(
+            DhcpOption::RelayAgentInformation(info.clone()).encode(),
+            Err(error.clone())
+        );
+
+        let mut options = crate::options::Options::new();
+        assert_eq!(
+            options.insert_decoded(DhcpOption::RelayAgentInformation(info)),
+            Err(error)
+        );
+        assert!(options.is_empty());
+    }
+
+    #[test]
+    fn test_build_and_echo() {
+        let info = RelayAgentInformation::new()
+            .with(RelaySubOption::CircuitId(b"eth0/1".to_vec()))
+            .with(RelaySubOption::ServerIdentifierOverride(Ipv4Addr::new(
+                10, 0, 0, 1,
+            )));
+        assert_eq!(
+            info.encode().unwrap(),
+            [1, 6, b'e', b't', b'h', b'0', b'/', b'1', 11, 4, 10, 0, 0, 1]
+        );
+
+        let mut buf = vec![0; 240];
+        buf[..3].copy_from_slice(&[1, 1, 6]);
+        buf[236..].copy_from_slice(&crate::packet::DHCP_COOKIE);
+        buf.extend_from_slice(&[53, 1, 1, 82, 14]);
+        buf.extend_from_slice(&info.encode().unwrap());
+        buf.push(255);
+
+        let request = Packet::try_from(buf.as_slice()).unwrap();
+        assert_eq!(request.relay_agent_information(), Some(Ok(info)));
+
+        let mut reply = request.clone();
+        reply.options.clear();
+        reply
+            .options
+            .insert(OptionCode::RelayAgentInformation, vec![1]);
+        reply.options.insert(OptionCode::DHCPMessageType, vec![2]);
+        reply.echo_relay_agent_information(&request);
+        assert_eq!(
+            reply.options.iter().last(),
+            request
+                .options
+                .iter()
+                .find(|(c, _)| *c == OptionCode::RelayAgentInformation)
+        );
+    }
+}
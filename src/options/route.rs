@@ -0,0 +1,202 @@
+use std::fmt;
+use std::net::Ipv4Addr;
+
+use super::{OptionCode, OptionError, Options};
+
+/// A route from the Classless Static Route option (121, RFC 3442).
+///
+/// On the wire each route is the prefix length, only the significant
+/// octets of the destination, then the router. A client that receives
+/// option 121 ignores the Router option (3), so a default route must be
+/// sent as a `0.0.0.0/0` entry.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct ClasslessRoute {
+    pub destination: Ipv4Addr,
+    pub prefix_len: u8,
+    pub router: Ipv4Addr,
+}
+
+impl ClasslessRoute {
+    /// Create a route, returning `None` if `prefix_len` is over 32 or the
+    /// destination has bits set past the prefix.
+    pub fn new(destination: Ipv4Addr, prefix_len: u8, router: Ipv4Addr) -> Option<Self> {
+        if prefix_len > 32 || u32::from(destination) & !prefix_mask(prefix_len) != 0 {
+            return None;
+        }
+
+        Some(ClasslessRoute {
+            destination,
+            prefix_len,
+            router,
+        })
+    }
+
+    /// Decode an RFC 3442 route list carried in option `code`.
+    pub fn decode_list(code: OptionCode, value: &[u8]) -> Result<Vec<Self>, OptionError> {
+        let mut routes = Vec::new();
+        let mut rest = value;
+
+        if value.is_empty() {
+            return Err(OptionError::InvalidLength { code, len: 0 });
+        }
+
+        while !rest.is_empty() {
+            let prefix_len = rest[0];
+            if prefix_len > 32 {
+                return Err(OptionError::InvalidValue { code });
+            }
+
+            let significant = (prefix_len as usize).div_ceil(8);
+            if rest.len() < 1 + significant + 4 {
+                return Err(OptionError::InvalidLength {
+                    code,
+                    len: value.len(),
+                });
+            }
+
+            let mut dest = [0; 4];
+            dest[..significant].copy_from_slice(&rest[1..1 + significant]);
+            let r = &rest[1 + significant..1 + significant + 4];
+
+            let route = ClasslessRoute::new(
+                Ipv4Addr::from(dest),
+                prefix_len,
+                Ipv4Addr::new(r[0], r[1], r[2], r[3]),
+            )
+            .ok_or(OptionError::InvalidValue { code })?;
+
+            routes.push(route);
+            rest = &rest[1 + significant + 4..];
+        }
+
+        Ok(routes)
+    }
+
+    /// Encode a route list in RFC 3442 form.
+    pub fn encode_list(routes: &[Self]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        for route in routes {
+            let significant = (route.prefix_len as usize).div_ceil(8);
+            bytes.push(route.prefix_len);
+            bytes.extend_from_slice(&route.destination.octets()[..significant]);
+            bytes.extend_from_slice(&route.router.octets());
+        }
+
+        bytes
+    }
+}
+
+impl fmt::Display for ClasslessRoute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} via {}",
+            self.destination, self.prefix_len, self.router
+        )
+    }
+}
+
+impl Options {
+    /// Set the Classless Static Route option to `routes`. If `microsoft` is
+    /// true the same routes are also set in option 249 for older Windows
+    /// clients that only understand the pre-standard code.
+    pub fn insert_classless_routes(&mut self, routes: Vec<ClasslessRoute>, microsoft: bool) {
+        let value = ClasslessRoute::encode_list(&routes);
+        if microsoft {
+            self.insert(OptionCode::MSClasslessRouteFormat, value.clone());
+        } else {
+            self.remove(OptionCode::MSClasslessRouteFormat);
+        }
+        self.insert(OptionCode::ClasslessRouteFormat, value);
+    }
+}
+
+fn prefix_mask(prefix_len: u8) -> u32 {
+    match prefix_len {
+        0 => 0,
+        n => !0u32 << (32 - n as u32),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::options::DhcpOption;
+
+    fn route(dest: [u8; 4], prefix_len: u8, router: [u8; 4]) -> ClasslessRoute {
+        ClasslessRoute::new(dest.into(), prefix_len, router.into()).unwrap()
+    }
+
+    #[test]
+    fn test_rfc3442_examples() {
+        let routes = vec![
+            route([0, 0, 0, 0], 0, [10, 0, 0, 1]),
+            route([10, 0, 0, 0], 8, [10, 0, 0, 1]),
+            route([10, 17, 0, 0], 16, [10, 0, 0, 1]),
+            route([10, 27, 129, 0], 24, [10, 0, 0, 1]),
+            route([10, 229, 0, 128], 25, [10, 0, 0, 1]),
+            route([10, 198, 122, 47], 32, [10, 0, 0, 1]),
+        ];
+        let bytes = [
+            0, 10, 0, 0, 1, // 0.0.0.0/0
+            8, 10, 10, 0, 0, 1, // 10.0.0.0/8
+            16, 10, 17, 10, 0, 0, 1, // 10.17.0.0/16
+            24, 10, 27, 129, 10, 0, 0, 1, // 10.27.129.0/24
+            25, 10, 229, 0, 128, 10, 0, 0, 1, // 10.229.0.128/25
+            32, 10, 198, 122, 47, 10, 0, 0, 1, // 10.198.122.47/32
+        ];
+
+        assert_eq!(ClasslessRoute::encode_list(&routes), bytes);
+        assert_eq!(
+            ClasslessRoute::decode_list(OptionCode::ClasslessRouteFormat, &bytes).unwrap(),
+            routes
+        );
+        assert_eq!(routes[4].to_string(), "10.229.0.128/25 via 10.0.0.1");
+    }
+
+    #[test]
+    fn test_malformed_routes() {
+        let code = OptionCode::ClasslessRouteFormat;
+
+        assert_eq!(
+            ClasslessRoute::decode_list(code, &[33, 10, 0, 0, 0, 0, 10, 0, 0, 1]),
+            Err(OptionError::InvalidValue { code })
+        );
+        assert_eq!(
+            ClasslessRoute::decode_list(code, &[24, 10, 0, 0, 10, 0, 0]),
+            Err(OptionError::InvalidLength { code, len: 7 })
+        );
+        // Bits set past the prefix length
+        assert_eq!(
+            ClasslessRoute::decode_list(code, &[8, 10, 10, 0, 0, 1, 7, 11, 10, 0, 0, 1]),
+            Err(OptionError::InvalidValue { code })
+        );
+        assert_eq!(
+            ClasslessRoute::decode_list(code, &[]),
+            Err(OptionError::InvalidLength { code, len: 0 })
+        );
+        assert!(
+            ClasslessRoute::new(Ipv4Addr::new(10, 0, 0, 1), 8, Ipv4Addr::UNSPECIFIED).is_none()
+        );
+    }
+
+    #[test]
+    fn test_mirror_microsoft_routes() {
+        let routes = vec![route([192, 168, 0, 0], 16, [10, 0, 0, 1])];
+        let mut options = Options::new();
+
+        options.insert_classless_routes(routes.clone(), true);
+        assert_eq!(
+            options[OptionCode::ClasslessRouteFormat],
+            [16, 192, 168, 10, 0, 0, 1]
+        );
+        assert_eq!(
+            options.get_decoded(OptionCode::MSClasslessRouteFormat),
+            Some(Ok(DhcpOption::MSClasslessRouteFormat(routes.clone())))
+        );
+
+        options.insert_classless_routes(routes, false);
+        assert!(!options.contains(OptionCode::MSClasslessRouteFormat));
+    }
+}
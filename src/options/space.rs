@@ -0,0 +1,439 @@
+use std::net::Ipv4Addr;
+
+use super::{OptionCode, OptionError};
+use crate::packet::Packet;
+
+/// How a sub-option's value is laid out.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SubOptionKind {
+    Bytes,
+    String,
+    U8,
+    U16,
+    U32,
+    Ipv4,
+    Ipv4List,
+    U16List,
+}
+
+/// A decoded sub-option value.
+#[derive(PartialEq, Clone, Debug)]
+pub enum SubOptionValue {
+    Bytes(Vec<u8>),
+    String(String),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    Ipv4(Ipv4Addr),
+    Ipv4List(Vec<Ipv4Addr>),
+    U16List(Vec<u16>),
+}
+
+impl SubOptionValue {
+    fn decode(kind: SubOptionKind, value: &[u8]) -> Option<Self> {
+        Some(match kind {
+            SubOptionKind::Bytes => SubOptionValue::Bytes(value.to_vec()),
+            SubOptionKind::String => {
+                SubOptionValue::String(String::from_utf8(value.to_vec()).ok()?)
+            }
+            SubOptionKind::U8 => match value {
+                [v] => SubOptionValue::U8(*v),
+                _ => return None,
+            },
+            SubOptionKind::U16 => match value {
+                [a, b] => SubOptionValue::U16(u16::from_be_bytes([*a, *b])),
+                _ => return None,
+            },
+            SubOptionKind::U32 => match value {
+                [a, b, c, d] => SubOptionValue::U32(u32::from_be_bytes([*a, *b, *c, *d])),
+                _ => return None,
+            },
+            SubOptionKind::Ipv4 => match value {
+                [a, b, c, d] => SubOptionValue::Ipv4(Ipv4Addr::new(*a, *b, *c, *d)),
+                _ => return None,
+            },
+            SubOptionKind::Ipv4List => {
+                if value.is_empty() || value.len() % 4 != 0 {
+                    return None;
+                }
+                SubOptionValue::Ipv4List(
+                    value
+                        .chunks(4)
+                        .map(|c| Ipv4Addr::new(c[0], c[1], c[2], c[3]))
+                        .collect(),
+                )
+            }
+            SubOptionKind::U16List => {
+                if value.is_empty() || value.len() % 2 != 0 {
+                    return None;
+                }
+                SubOptionValue::U16List(
+                    value
+                        .chunks(2)
+                        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                        .collect(),
+                )
+            }
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            SubOptionValue::Bytes(v) => v.clone(),
+            SubOptionValue::String(s) => s.as_bytes().to_vec(),
+            SubOptionValue::U8(v) => vec![*v],
+            SubOptionValue::U16(v) => v.to_be_bytes().to_vec(),
+            SubOptionValue::U32(v) => v.to_be_bytes().to_vec(),
+            SubOptionValue::Ipv4(addr) => addr.octets().to_vec(),
+            SubOptionValue::Ipv4List(addrs) => addrs.iter().flat_map(|a| a.octets()).collect(),
+            SubOptionValue::U16List(v) => v.iter().flat_map(|v| v.to_be_bytes()).collect(),
+        }
+    }
+}
+
+/// A sub-option of the Vendor-Specific Information option (43).
+#[derive(PartialEq, Clone, Debug)]
+pub struct VendorSubOption {
+    pub code: u8,
+    pub value: SubOptionValue,
+}
+
+impl VendorSubOption {
+    pub fn new(code: u8, value: SubOptionValue) -> Self {
+        VendorSubOption { code, value }
+    }
+}
+
+/// The sub-options a vendor defines for option 43.
+///
+/// Sub-options without a definition decode as `SubOptionValue::Bytes`.
+#[derive(Clone, Debug)]
+pub struct OptionSpace {
+    name: String,
+    definitions: Vec<(u8, String, SubOptionKind)>,
+    terminated: bool,
+}
+
+impl OptionSpace {
+    /// An empty space whose encoded values have no End sub-option.
+    pub fn new(name: impl Into<String>) -> Self {
+        OptionSpace {
+            name: name.into(),
+            definitions: Vec::new(),
+            terminated: false,
+        }
+    }
+
+    /// End encoded values with an End sub-option (255), as PXE does.
+    pub fn terminated(mut self) -> Self {
+        self.terminated = true;
+        self
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+
+    /// Define sub-option `code`, replacing any earlier definition.
+    pub fn define(mut self, code: u8, name: impl Into<String>, kind: SubOptionKind) -> Self {
+        self.definitions.retain(|(c, _, _)| *c != code);
+        self.definitions.push((code, name.into(), kind));
+        self
+    }
+
+    /// The PXE space (PXE 2.1 specification), used by clients whose vendor
+    /// class starts with "PXEClient".
+    ///
+    /// The boot server, boot menu and menu prompt sub-options have their own
+    /// layouts and are left as bytes.
+    pub fn pxe() -> Self {
+        OptionSpace::new("PXE")
+            .terminated()
+            .define(1, "MTFTP IP", SubOptionKind::Ipv4)
+            .define(2, "MTFTP client port", SubOptionKind::U16)
+            .define(3, "MTFTP server port", SubOptionKind::U16)
+            .define(4, "MTFTP timeout", SubOptionKind::U8)
+            .define(5, "MTFTP delay", SubOptionKind::U8)
+            .define(6, "Discovery control", SubOptionKind::U8)
+            .define(7, "Discovery multicast address", SubOptionKind::Ipv4)
+            .define(8, "Boot servers", SubOptionKind::Bytes)
+            .define(9, "Boot menu", SubOptionKind::Bytes)
+            .define(10, "Menu prompt", SubOptionKind::Bytes)
+            .define(71, "Boot item", SubOptionKind::U16List)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The name given to sub-option `code`, if it is defined.
+    pub fn sub_option_name(&self, code: u8) -> Option<&str> {
+        self.definition(code).map(|(_, name, _)| name.as_str())
+    }
+
+    /// How sub-option `code` is decoded.
+    pub fn kind(&self, code: u8) -> SubOptionKind {
+        self.definition(code)
+            .map_or(SubOptionKind::Bytes, |(_, _, kind)| *kind)
+    }
+
+    fn definition(&self, code: u8) -> Option<&(u8, String, SubOptionKind)> {
+        self.definitions.iter().find(|(c, _, _)| *c == code)
+    }
+
+    /// Decode the value of option 43. Pad sub-options are skipped and
+    /// decoding stops at an End sub-option.
+    pub fn decode(&self, value: &[u8]) -> Result<Vec<VendorSubOption>, OptionError> {
+        let code = OptionCode::VendorSpecificInformation;
+        let mut list = Vec::new();
+        let mut rest = value;
+
+        while let Some(&sub_code) = rest.first() {
+            match sub_code {
+                0 => {
+                    rest = &rest[1..];
+                    continue;
+                }
+                255 => break,
+                _ => {}
+            }
+
+            if rest.len() < 2 || rest.len() < 2 + rest[1] as usize {
+                return Err(OptionError::InvalidLength {
+                    code,
+                    len: value.len(),
+                });
+            }
+
+            let len = rest[1] as usize;
+            let value = SubOptionValue::decode(self.kind(sub_code), &rest[2..2 + len])
+                .ok_or(OptionError::InvalidValue { code })?;
+            list.push(VendorSubOption::new(sub_code, value));
+            rest = &rest[2 + len..];
+        }
+
+        Ok(list)
+    }
+
+    /// Encode sub-options as the value of option 43, followed by an End
+    /// sub-option if the space is terminated.
+    ///
+    /// Fails if a sub-option value is longer than 255 bytes.
+    pub fn encode(&self, list: &[VendorSubOption]) -> Result<Vec<u8>, OptionError> {
+        let mut bytes = Vec::new();
+
+        for sub in list {
+            let value = sub.value.encode();
+            if value.len() > 255 {
+                return Err(OptionError::InvalidLength {
+                    code: OptionCode::VendorSpecificInformation,
+                    len: value.len(),
+                });
+            }
+            bytes.push(sub.code);
+            bytes.push(value.len() as u8);
+            bytes.extend_from_slice(&value);
+        }
+        if self.terminated {
+            bytes.push(255);
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Chooses the option space for option 43 from the client's vendor class
+/// identifier (option 60).
+///
+/// Spaces are registered against a vendor class prefix and the longest
+/// matching prefix wins.
+///
+/// ```
+/// use dhcp_parser::options::{OptionSpace, OptionSpaceRegistry, SubOptionKind};
+///
+/// let mut registry = OptionSpaceRegistry::with_builtin();
+/// registry.register(
+///     "ubnt",
+///     OptionSpace::new("Ubiquiti").define(1, "Controller", SubOptionKind::Ipv4),
+/// );
+///
+/// let space = registry.lookup(b"PXEClient:Arch:00007:UNDI:003016").unwrap();
+/// assert_eq!(space.name(), "PXE");
+/// assert!(registry.lookup(b"MSFT 5.0").is_none());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct OptionSpaceRegistry {
+    spaces: Vec<(Vec<u8>, OptionSpace)>,
+}
+
+impl OptionSpaceRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        OptionSpaceRegistry::default()
+    }
+
+    /// A registry with the built-in PXE space.
+    pub fn with_builtin() -> Self {
+        let mut registry = OptionSpaceRegistry::new();
+        registry.register("PXEClient", OptionSpace::pxe());
+        registry
+    }
+
+    /// Use `space` for vendor classes starting with `prefix`, replacing any
+    /// space registered for the same prefix.
+    pub fn register(&mut self, prefix: impl AsRef<[u8]>, space: OptionSpace) {
+        let prefix = prefix.as_ref();
+        self.spaces.retain(|(p, _)| p != prefix);
+        self.spaces.push((prefix.to_vec(), space));
+    }
+
+    pub fn lookup(&self, vendor_class: &[u8]) -> Option<&OptionSpace> {
+        self.spaces
+            .iter()
+            .filter(|(prefix, _)| vendor_class.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, space)| space)
+    }
+
+    /// Decode an option 43 value for `vendor_class`. Returns None if no
+    /// space is registered for it.
+    pub fn decode(
+        &self,
+        vendor_class: &[u8],
+        value: &[u8],
+    ) -> Option<Result<Vec<VendorSubOption>, OptionError>> {
+        self.lookup(vendor_class).map(|space| space.decode(value))
+    }
+
+    /// Encode sub-options as an option 43 value for `vendor_class`.
+    /// Returns None if no space is registered for it.
+    pub fn encode(
+        &self,
+        vendor_class: &[u8],
+        list: &[VendorSubOption],
+    ) -> Option<Result<Vec<u8>, OptionError>> {
+        self.lookup(vendor_class).map(|space| space.encode(list))
+    }
+}
+
+impl Packet {
+    /// Decode option 43 using the space `registry` picks for this packet's
+    /// vendor class identifier.
+    ///
+    /// Returns None if either option is missing or no space matches. A
+    /// server decoding its own reply should look up the space with the
+    /// request's vendor class instead.
+    pub fn vendor_sub_options(
+        &self,
+        registry: &OptionSpaceRegistry,
+    ) -> Option<Result<Vec<VendorSubOption>, OptionError>> {
+        let vendor_class = self.options.get(OptionCode::VendorClassIdentifier)?;
+        let value = self.options.get(OptionCode::VendorSpecificInformation)?;
+        registry.decode(vendor_class, value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pxe_space() {
+        let value = [
+            6, 1, 8, // discovery control
+            71, 4, 0x80, 0x00, 0, 0, // boot item
+            9, 6, 0x80, 0x00, 3, b'a', b'b', b'c', // boot menu
+            0,    // pad
+            255,
+        ];
+
+        let list = OptionSpace::pxe().decode(&value).unwrap();
+        assert_eq!(
+            list,
+            vec![
+                VendorSubOption::new(6, SubOptionValue::U8(8)),
+                VendorSubOption::new(71, SubOptionValue::U16List(vec![0x8000, 0])),
+                VendorSubOption::new(9, SubOptionValue::Bytes(vec![0x80, 0, 3, b'a', b'b', b'c'])),
+            ]
+        );
+
+        let mut without_pad = value.to_vec();
+        without_pad.remove(17);
+        assert_eq!(OptionSpace::pxe().encode(&list), Ok(without_pad));
+    }
+
+    #[test]
+    fn test_unterminated_space() {
+        let mut registry = OptionSpaceRegistry::with_builtin();
+        registry.register(
+            "ubnt",
+            OptionSpace::new("Ubiquiti").define(1, "Controller", SubOptionKind::Ipv4),
+        );
+
+        let value = [1, 4, 10, 0, 0, 1];
+        let list = registry.decode(b"ubnt", &value).unwrap().unwrap();
+        assert_eq!(registry.encode(b"ubnt", &list), Some(Ok(value.to_vec())));
+        assert!(registry.encode(b"MSFT 5.0", &list).is_none());
+
+        let long = [VendorSubOption::new(1, SubOptionValue::Bytes(vec![0; 256]))];
+        assert_eq!(
+            registry.encode(b"PXEClient", &long),
+            Some(Err(OptionError::InvalidLength {
+                code: OptionCode::VendorSpecificInformation,
+                len: 256
+            }))
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let code = OptionCode::VendorSpecificInformation;
+        let pxe = OptionSpace::pxe();
+
+        assert_eq!(
+            pxe.decode(&[6, 2, 1, 1]),
+            Err(OptionError::InvalidValue { code })
+        );
+        assert_eq!(
+            pxe.decode(&[6, 3, 1]),
+            Err(OptionError::InvalidLength { code, len: 3 })
+        );
+        assert_eq!(
+            pxe.decode(&[200, 1, 9]),
+            Ok(vec![VendorSubOption::new(
+                200,
+                SubOptionValue::Bytes(vec![9])
+            )])
+        );
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = OptionSpaceRegistry::with_builtin();
+        registry.register(
+            "PXEClient:Arch:00007",
+            OptionSpace::new("UEFI PXE").define(1, "Server", SubOptionKind::String),
+        );
+
+        assert_eq!(registry.lookup(b"PXEClient").unwrap().name(), "PXE");
+        assert_eq!(
+            registry
+                .lookup(b"PXEClient:Arch:00007:UNDI:003016")
+                .unwrap()
+                .name(),
+            "UEFI PXE"
+        );
+        assert_eq!(
+            registry.decode(b"PXEClient:Arch:00007", &[1, 1, b'x', 255]),
+            Some(Ok(vec![VendorSubOption::new(
+                1,
+                SubOptionValue::String("x".to_string())
+            )]))
+        );
+        assert!(registry.decode(b"MSFT 5.0", &[1, 1, 0]).is_none());
+
+        let pxe = OptionSpace::pxe();
+        assert_eq!(pxe.sub_option_name(6), Some("Discovery control"));
+        assert_eq!(pxe.kind(200), SubOptionKind::Bytes);
+    }
+}
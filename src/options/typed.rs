@@ -0,0 +1,814 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use super::{
+    ClasslessRoute, ClientFqdn, ClientIdentifier, ClientNetworkInterface, DnsName, MessageType,
+    OptionCode, Overload, ProcessorArchitecture, RelayAgentInformation, Uuid, VendorClass,
+    VendorOptions,
+};
+
+/// Reasons an option value cannot be decoded or encoded.
+#[derive(PartialEq, Clone, Debug)]
+pub enum OptionError {
+    /// The value's length is not valid for the option.
+    InvalidLength { code: OptionCode, len: usize },
+    /// The value has a valid length but its contents are not, such as a
+    /// flag other than 0 or 1 or a name that is not UTF-8.
+    InvalidValue { code: OptionCode },
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptionError::InvalidLength { code, len } => {
+                write!(f, "option {} has invalid length {}", code, len)
+            }
+            OptionError::InvalidValue { code } => write!(f, "option {} has an invalid value", code),
+        }
+    }
+}
+
+impl Error for OptionError {}
+
+/// A decoded option value.
+///
+/// Each variant corresponds to the `OptionCode` of the same name and holds
+/// the value in its natural type. Codes without a typed codec decode to
+/// `Raw`.
+#[derive(PartialEq, Clone, Debug)]
+pub enum DhcpOption {
+    End,
+    Pad,
+    SubnetMask(Ipv4Addr),
+    /// Offset from UTC in seconds.
+    TimeOffset(i32),
+    Router(Vec<Ipv4Addr>),
+    TimeServer(Vec<Ipv4Addr>),
+    NameServer(Vec<Ipv4Addr>),
+    DomainNameServer(Vec<Ipv4Addr>),
+    LogServer(Vec<Ipv4Addr>),
+    CookieServer(Vec<Ipv4Addr>),
+    LPRServer(Vec<Ipv4Addr>),
+    ImpressServer(Vec<Ipv4Addr>),
+    ResourceLocationServer(Vec<Ipv4Addr>),
+    HostName(String),
+    /// Size of the boot file in 512 byte blocks.
+    BootFileSize(u16),
+    MeritDumpFile(String),
+    DomainName(String),
+    SwapServer(Ipv4Addr),
+    RootPath(String),
+    ExtensionsPath(String),
+    IPForwardingEnableDisable(bool),
+    NonLocalSourceRoutingEnableDisable(bool),
+    /// Pairs of destination address and mask.
+    PolicyFilter(Vec<(Ipv4Addr, Ipv4Addr)>),
+    MaximumDatagramReassemblySize(u16),
+    DefaultIPTimeToLive(u8),
+    PathMTUAgingTimeout(Duration),
+    PathMTUPlateauTable(Vec<u16>),
+    InterfaceMTU(u16),
+    AllSubnetsAreLocal(bool),
+    BroadcastAddress(Ipv4Addr),
+    PerformMaskDiscovery(bool),
+    MaskSupplier(bool),
+    PerformRouterDiscovery(bool),
+    RouterSolicitationAddress(Ipv4Addr),
+    /// Pairs of destination and router.
+    StaticRoute(Vec<(Ipv4Addr, Ipv4Addr)>),
+    TrailerEncapsulation(bool),
+    ARPCacheTimeout(Duration),
+    EthernetEncapsulation(bool),
+    TCPDefaultTTL(u8),
+    TCPKeepaliveInterval(Duration),
+    TCPKeepaliveGarbage(bool),
+    NetworkInformationServiceDomain(String),
+    NetworkInformationServers(Vec<Ipv4Addr>),
+    NetworkTimeProtocolServers(Vec<Ipv4Addr>),
+    VendorSpecificInformation(Vec<u8>),
+    NetBIOSOverTCPIPNameServer(Vec<Ipv4Addr>),
+    NetBIOSOverTCPIPDatagramDistributionServer(Vec<Ipv4Addr>),
+    /// NetBIOS node type: 1 B-node, 2 P-node, 4 M-node, 8 H-node.
+    NetBIOSOverTCPIPNodeType(u8),
+    NetBIOSOverTCPIPScope(String),
+    XWindowSystemFontServer(Vec<Ipv4Addr>),
+    XWindowSystemDisplayManager(Vec<Ipv4Addr>),
+    NetworkInformationServicePlusDomain(String),
+    NetworkInformationServicePlusServers(Vec<Ipv4Addr>),
+    MobileIPHomeAgent(Vec<Ipv4Addr>),
+    SimpleMailTransportProtocol(Vec<Ipv4Addr>),
+    PostOfficeProtocolServer(Vec<Ipv4Addr>),
+    NetworkNewsTransportProtocol(Vec<Ipv4Addr>),
+    DefaultWorldWideWebServer(Vec<Ipv4Addr>),
+    DefaultFingerServer(Vec<Ipv4Addr>),
+    DefaultInternetRelayChatServer(Vec<Ipv4Addr>),
+    StreetTalkServer(Vec<Ipv4Addr>),
+    StreetTalkDirectoryAssistance(Vec<Ipv4Addr>),
+    ClientFQDN(ClientFqdn),
+    RelayAgentInformation(RelayAgentInformation),
+    RequestedIPAddress(Ipv4Addr),
+    IPAddressLeaseTime(Duration),
+    Overload(Overload),
+    DHCPMessageType(MessageType),
+    ServerIdentifier(Ipv4Addr),
+    ParameterRequestList(Vec<OptionCode>),
+    Message(String),
+    MaximumDHCPMessageSize(u16),
+    RenewalTimeValue(Duration),
+    RebindingTimeValue(Duration),
+    VendorClassIdentifier(Vec<u8>),
+    ClientIdentifier(ClientIdentifier),
+    TFTPServerName(String),
+    BootFileName(String),
+    UserClass(Vec<u8>),
+    /// Client system architecture types (RFC 4578).
+    ClientArchitecture(Vec<ProcessorArchitecture>),
+    ClientNetworkInterfaceIdentifier(ClientNetworkInterface),
+    ClientMachineIdentifier(Uuid),
+    TZPOSIXString(String),
+    TZDatabaseString(String),
+    /// Domain search list (RFC 3397), compressed on the wire.
+    DomainSearch(Vec<DnsName>),
+    ClasslessRouteFormat(Vec<ClasslessRoute>),
+    VendorIdentifyingVendorClass(Vec<VendorClass>),
+    VendorIdentifyingVendorSpecificInformation(Vec<VendorOptions>),
+    /// Microsoft's pre-standard copy of `ClasslessRouteFormat`.
+    MSClasslessRouteFormat(Vec<ClasslessRoute>),
+    /// An option without a typed codec, such as a site-specific code or
+    /// `RapidCommit`.
+    Raw(OptionCode, Vec<u8>),
+}
+
+impl DhcpOption {
+    /// The option code this value is carried under.
+    pub fn code(&self) -> OptionCode {
+        match self {
+            DhcpOption::End => OptionCode::End,
+            DhcpOption::Pad => OptionCode::Pad,
+            DhcpOption::SubnetMask(_) => OptionCode::SubnetMask,
+            DhcpOption::TimeOffset(_) => OptionCode::TimeOffset,
+            DhcpOption::Router(_) => OptionCode::Router,
+            DhcpOption::TimeServer(_) => OptionCode::TimeServer,
+            DhcpOption::NameServer(_) => OptionCode::NameServer,
+            DhcpOption::DomainNameServer(_) => OptionCode::DomainNameServer,
+            DhcpOption::LogServer(_) => OptionCode::LogServer,
+            DhcpOption::CookieServer(_) => OptionCode::CookieServer,
+            DhcpOption::LPRServer(_) => OptionCode::LPRServer,
+            DhcpOption::ImpressServer(_) => OptionCode::ImpressServer,
+            DhcpOption::ResourceLocationServer(_) => OptionCode::ResourceLocationServer,
+            DhcpOption::HostName(_) => OptionCode::HostName,
+            DhcpOption::BootFileSize(_) => OptionCode::BootFileSize,
+            DhcpOption::MeritDumpFile(_) => OptionCode::MeritDumpFile,
+            DhcpOption::DomainName(_) => OptionCode::DomainName,
+            DhcpOption::SwapServer(_) => OptionCode::SwapServer,
+            DhcpOption::RootPath(_) => OptionCode::RootPath,
+            DhcpOption::ExtensionsPath(_) => OptionCode::ExtensionsPath,
+            DhcpOption::IPForwardingEnableDisable(_) => OptionCode::IPForwardingEnableDisable,
+            DhcpOption::NonLocalSourceRoutingEnableDisable(_) => {
+                OptionCode::NonLocalSourceRoutingEnableDisable
+            }
+            DhcpOption::PolicyFilter(_) => OptionCode::PolicyFilter,
+            DhcpOption::MaximumDatagramReassemblySize(_) => {
+                OptionCode::MaximumDatagramReassemblySize
+            }
+            DhcpOption::DefaultIPTimeToLive(_) => OptionCode::DefaultIPTimeToLive,
+            DhcpOption::PathMTUAgingTimeout(_) => OptionCode::PathMTUAgingTimeout,
+            DhcpOption::PathMTUPlateauTable(_) => OptionCode::PathMTUPlateauTable,
+            DhcpOption::InterfaceMTU(_) => OptionCode::InterfaceMTU,
+            DhcpOption::AllSubnetsAreLocal(_) => OptionCode::AllSubnetsAreLocal,
+            DhcpOption::BroadcastAddress(_) => OptionCode::BroadcastAddress,
+            DhcpOption::PerformMaskDiscovery(_) => OptionCode::PerformMaskDiscovery,
+            DhcpOption::MaskSupplier(_) => OptionCode::MaskSupplier,
+            DhcpOption::PerformRouterDiscovery(_) => OptionCode::PerformRouterDiscovery,
+            DhcpOption::RouterSolicitationAddress(_) => OptionCode::RouterSolicitationAddress,
+            DhcpOption::StaticRoute(_) => OptionCode::StaticRoute,
+            DhcpOption::TrailerEncapsulation(_) => OptionCode::TrailerEncapsulation,
+            DhcpOption::ARPCacheTimeout(_) => OptionCode::ARPCacheTimeout,
+            DhcpOption::EthernetEncapsulation(_) => OptionCode::EthernetEncapsulation,
+            DhcpOption::TCPDefaultTTL(_) => OptionCode::TCPDefaultTTL,
+            DhcpOption::TCPKeepaliveInterval(_) => OptionCode::TCPKeepaliveInterval,
+            DhcpOption::TCPKeepaliveGarbage(_) => OptionCode::TCPKeepaliveGarbage,
+            DhcpOption::NetworkInformationServiceDomain(_) => {
+                OptionCode::NetworkInformationServiceDomain
+            }
+            DhcpOption::NetworkInformationServers(_) => OptionCode::NetworkInformationServers,
+            DhcpOption::NetworkTimeProtocolServers(_) => OptionCode::NetworkTimeProtocolServers,
+            DhcpOption::VendorSpecificInformation(_) => OptionCode::VendorSpecificInformation,
+            DhcpOption::NetBIOSOverTCPIPNameServer(_) => OptionCode::NetBIOSOverTCPIPNameServer,
+            DhcpOption::NetBIOSOverTCPIPDatagramDistributionServer(_) => {
+                OptionCode::NetBIOSOverTCPIPDatagramDistributionServer
+            }
+            DhcpOption::NetBIOSOverTCPIPNodeType(_) => OptionCode::NetBIOSOverTCPIPNodeType,
+            DhcpOption::NetBIOSOverTCPIPScope(_) => OptionCode::NetBIOSOverTCPIPScope,
+            DhcpOption::XWindowSystemFontServer(_) => OptionCode::XWindowSystemFontServer,
+            DhcpOption::XWindowSystemDisplayManager(_) => OptionCode::XWindowSystemDisplayManager,
+            DhcpOption::NetworkInformationServicePlusDomain(_) => {
+                OptionCode::NetworkInformationServicePlusDomain
+            }
+            DhcpOption::NetworkInformationServicePlusServers(_) => {
+                OptionCode::NetworkInformationServicePlusServers
+            }
+            DhcpOption::MobileIPHomeAgent(_) => OptionCode::MobileIPHomeAgent,
+            DhcpOption::SimpleMailTransportProtocol(_) => OptionCode::SimpleMailTransportProtocol,
+            DhcpOption::PostOfficeProtocolServer(_) => OptionCode::PostOfficeProtocolServer,
+            DhcpOption::NetworkNewsTransportProtocol(_) => OptionCode::NetworkNewsTransportProtocol,
+            DhcpOption::DefaultWorldWideWebServer(_) => OptionCode::DefaultWorldWideWebServer,
+            DhcpOption::DefaultFingerServer(_) => OptionCode::DefaultFingerServer,
+            DhcpOption::DefaultInternetRelayChatServer(_) => {
+                OptionCode::DefaultInternetRelayChatServer
+            }
+            DhcpOption::StreetTalkServer(_) => OptionCode::StreetTalkServer,
+            DhcpOption::StreetTalkDirectoryAssistance(_) => {
+                OptionCode::StreetTalkDirectoryAssistance
+            }
+            DhcpOption::ClientFQDN(_) => OptionCode::ClientFQDN,
+            DhcpOption::RelayAgentInformation(_) => OptionCode::RelayAgentInformation,
+            DhcpOption::RequestedIPAddress(_) => OptionCode::RequestedIPAddress,
+            DhcpOption::IPAddressLeaseTime(_) => OptionCode::IPAddressLeaseTime,
+            DhcpOption::Overload(_) => OptionCode::Overload,
+            DhcpOption::DHCPMessageType(_) => OptionCode::DHCPMessageType,
+            DhcpOption::ServerIdentifier(_) => OptionCode::ServerIdentifier,
+            DhcpOption::ParameterRequestList(_) => OptionCode::ParameterRequestList,
+            DhcpOption::Message(_) => OptionCode::Message,
+            DhcpOption::MaximumDHCPMessageSize(_) => OptionCode::MaximumDHCPMessageSize,
+            DhcpOption::RenewalTimeValue(_) => OptionCode::RenewalTimeValue,
+            DhcpOption::RebindingTimeValue(_) => OptionCode::RebindingTimeValue,
+            DhcpOption::VendorClassIdentifier(_) => OptionCode::VendorClassIdentifier,
+            DhcpOption::ClientIdentifier(_) => OptionCode::ClientIdentifier,
+            DhcpOption::TFTPServerName(_) => OptionCode::TFTPServerName,
+            DhcpOption::BootFileName(_) => OptionCode::BootFileName,
+            DhcpOption::UserClass(_) => OptionCode::UserClass,
+            DhcpOption::ClientArchitecture(_) => OptionCode::ClientArchitecture,
+            DhcpOption::ClientNetworkInterfaceIdentifier(_) => {
+                OptionCode::ClientNetworkInterfaceIdentifier
+            }
+            DhcpOption::ClientMachineIdentifier(_) => OptionCode::ClientMachineIdentifier,
+            DhcpOption::TZPOSIXString(_) => OptionCode::TZPOSIXString,
+            DhcpOption::TZDatabaseString(_) => OptionCode::TZDatabaseString,
+            DhcpOption::DomainSearch(_) => OptionCode::DomainSearch,
+            DhcpOption::ClasslessRouteFormat(_) => OptionCode::ClasslessRouteFormat,
+            DhcpOption::VendorIdentifyingVendorClass(_) => OptionCode::VendorIdentifyingVendorClass,
+            DhcpOption::VendorIdentifyingVendorSpecificInformation(_) => {
+                OptionCode::VendorIdentifyingVendorSpecificInformation
+            }
+            DhcpOption::MSClasslessRouteFormat(_) => OptionCode::MSClasslessRouteFormat,
+            DhcpOption::Raw(code, _) => *code,
+        }
+    }
+
+    /// Decode the value of option `code`.
+    pub fn decode(code: OptionCode, value: &[u8]) -> Result<Self, OptionError> {
+        Ok(match code {
+            OptionCode::End => {
+                check_len(code, value, value.is_empty())?;
+                DhcpOption::End
+            }
+            OptionCode::Pad => {
+                check_len(code, value, value.is_empty())?;
+                DhcpOption::Pad
+            }
+            OptionCode::SubnetMask => DhcpOption::SubnetMask(ip(code, value)?),
+            OptionCode::TimeOffset => DhcpOption::TimeOffset(long(code, value)? as i32),
+            OptionCode::Router => DhcpOption::Router(ip_list(code, value, 1)?),
+            OptionCode::TimeServer => DhcpOption::TimeServer(ip_list(code, value, 1)?),
+            OptionCode::NameServer => DhcpOption::NameServer(ip_list(code, value, 1)?),
+            OptionCode::DomainNameServer => DhcpOption::DomainNameServer(ip_list(code, value, 1)?),
+            OptionCode::LogServer => DhcpOption::LogServer(ip_list(code, value, 1)?),
+            OptionCode::CookieServer => DhcpOption::CookieServer(ip_list(code, value, 1)?),
+            OptionCode::LPRServer => DhcpOption::LPRServer(ip_list(code, value, 1)?),
+            OptionCode::ImpressServer => DhcpOption::ImpressServer(ip_list(code, value, 1)?),
+            OptionCode::ResourceLocationServer => {
+                DhcpOption::ResourceLocationServer(ip_list(code, value, 1)?)
+            }
+            OptionCode::HostName => DhcpOption::HostName(string(code, value)?),
+            OptionCode::BootFileSize => DhcpOption::BootFileSize(short(code, value)?),
+            OptionCode::MeritDumpFile => DhcpOption::MeritDumpFile(string(code, value)?),
+            OptionCode::DomainName => DhcpOption::DomainName(string(code, value)?),
+            OptionCode::SwapServer => DhcpOption::SwapServer(ip(code, value)?),
+            OptionCode::RootPath => DhcpOption::RootPath(string(code, value)?),
+            OptionCode::ExtensionsPath => DhcpOption::ExtensionsPath(string(code, value)?),
+            OptionCode::IPForwardingEnableDisable => {
+                DhcpOption::IPForwardingEnableDisable(flag(code, value)?)
+            }
+            OptionCode::NonLocalSourceRoutingEnableDisable => {
+                DhcpOption::NonLocalSourceRoutingEnableDisable(flag(code, value)?)
+            }
+            OptionCode::PolicyFilter => DhcpOption::PolicyFilter(ip_pair_list(code, value)?),
+            OptionCode::MaximumDatagramReassemblySize => {
+                DhcpOption::MaximumDatagramReassemblySize(short(code, value)?)
+            }
+            OptionCode::DefaultIPTimeToLive => DhcpOption::DefaultIPTimeToLive(byte(code, value)?),
+            OptionCode::PathMTUAgingTimeout => {
+                DhcpOption::PathMTUAgingTimeout(Duration::from_secs(long(code, value)? as u64))
+            }
+            OptionCode::PathMTUPlateauTable => {
+                DhcpOption::PathMTUPlateauTable(short_list(code, value)?)
+            }
+            OptionCode::InterfaceMTU => DhcpOption::InterfaceMTU(short(code, value)?),
+            OptionCode::AllSubnetsAreLocal => DhcpOption::AllSubnetsAreLocal(flag(code, value)?),
+            OptionCode::BroadcastAddress => DhcpOption::BroadcastAddress(ip(code, value)?),
+            OptionCode::PerformMaskDiscovery => {
+                DhcpOption::PerformMaskDiscovery(flag(code, value)?)
+            }
+            OptionCode::MaskSupplier => DhcpOption::MaskSupplier(flag(code, value)?),
+            OptionCode::PerformRouterDiscovery => {
+                DhcpOption::PerformRouterDiscovery(flag(code, value)?)
+            }
+            OptionCode::RouterSolicitationAddress => {
+                DhcpOption::RouterSolicitationAddress(ip(code, value)?)
+            }
+            OptionCode::StaticRoute => DhcpOption::StaticRoute(ip_pair_list(code, value)?),
+            OptionCode::TrailerEncapsulation => {
+                DhcpOption::TrailerEncapsulation(flag(code, value)?)
+            }
+            OptionCode::ARPCacheTimeout => {
+                DhcpOption::ARPCacheTimeout(Duration::from_secs(long(code, value)? as u64))
+            }
+            OptionCode::EthernetEncapsulation => {
+                DhcpOption::EthernetEncapsulation(flag(code, value)?)
+            }
+            OptionCode::TCPDefaultTTL => DhcpOption::TCPDefaultTTL(byte(code, value)?),
+            OptionCode::TCPKeepaliveInterval => {
+                DhcpOption::TCPKeepaliveInterval(Duration::from_secs(long(code, value)? as u64))
+            }
+            OptionCode::TCPKeepaliveGarbage => DhcpOption::TCPKeepaliveGarbage(flag(code, value)?),
+            OptionCode::NetworkInformationServiceDomain => {
+                DhcpOption::NetworkInformationServiceDomain(string(code, value)?)
+            }
+            OptionCode::NetworkInformationServers => {
+                DhcpOption::NetworkInformationServers(ip_list(code, value, 1)?)
+            }
+            OptionCode::NetworkTimeProtocolServers => {
+                DhcpOption::NetworkTimeProtocolServers(ip_list(code, value, 1)?)
+            }
+            OptionCode::VendorSpecificInformation => {
+                DhcpOption::VendorSpecificInformation(bytes(code, value, 1)?)
+            }
+            OptionCode::NetBIOSOverTCPIPNameServer => {
+                DhcpOption::NetBIOSOverTCPIPNameServer(ip_list(code, value, 1)?)
+            }
+            OptionCode::NetBIOSOverTCPIPDatagramDistributionServer => {
+                DhcpOption::NetBIOSOverTCPIPDatagramDistributionServer(ip_list(code, value, 1)?)
+            }
+            OptionCode::NetBIOSOverTCPIPNodeType => {
+                DhcpOption::NetBIOSOverTCPIPNodeType(byte(code, value)?)
+            }
+            OptionCode::NetBIOSOverTCPIPScope => {
+                DhcpOption::NetBIOSOverTCPIPScope(string(code, value)?)
+            }
+            OptionCode::XWindowSystemFontServer => {
+                DhcpOption::XWindowSystemFontServer(ip_list(code, value, 1)?)
+            }
+            OptionCode::XWindowSystemDisplayManager => {
+                DhcpOption::XWindowSystemDisplayManager(ip_list(code, value, 1)?)
+            }
+            OptionCode::NetworkInformationServicePlusDomain => {
+                DhcpOption::NetworkInformationServicePlusDomain(string(code, value)?)
+            }
+            OptionCode::NetworkInformationServicePlusServers => {
+                DhcpOption::NetworkInformationServicePlusServers(ip_list(code, value, 1)?)
+            }
+            OptionCode::MobileIPHomeAgent => {
+                DhcpOption::MobileIPHomeAgent(ip_list(code, value, 0)?)
+            }
+            OptionCode::SimpleMailTransportProtocol => {
+                DhcpOption::SimpleMailTransportProtocol(ip_list(code, value, 1)?)
+            }
+            OptionCode::PostOfficeProtocolServer => {
+                DhcpOption::PostOfficeProtocolServer(ip_list(code, value, 1)?)
+            }
+            OptionCode::NetworkNewsTransportProtocol => {
+                DhcpOption::NetworkNewsTransportProtocol(ip_list(code, value, 1)?)
+            }
+            OptionCode::DefaultWorldWideWebServer => {
+                DhcpOption::DefaultWorldWideWebServer(ip_list(code, value, 1)?)
+            }
+            OptionCode::DefaultFingerServer => {
+                DhcpOption::DefaultFingerServer(ip_list(code, value, 1)?)
+            }
+            OptionCode::DefaultInternetRelayChatServer => {
+                DhcpOption::DefaultInternetRelayChatServer(ip_list(code, value, 1)?)
+            }
+            OptionCode::StreetTalkServer => DhcpOption::StreetTalkServer(ip_list(code, value, 1)?),
+            OptionCode::StreetTalkDirectoryAssistance => {
+                DhcpOption::StreetTalkDirectoryAssistance(ip_list(code, value, 1)?)
+            }
+            OptionCode::ClientFQDN => DhcpOption::ClientFQDN(ClientFqdn::decode(value)?),
+            OptionCode::RelayAgentInformation => {
+                DhcpOption::RelayAgentInformation(RelayAgentInformation::decode(value)?)
+            }
+            OptionCode::RequestedIPAddress => DhcpOption::RequestedIPAddress(ip(code, value)?),
+            OptionCode::IPAddressLeaseTime => {
+                DhcpOption::IPAddressLeaseTime(Duration::from_secs(long(code, value)? as u64))
+            }
+            OptionCode::Overload => DhcpOption::Overload(
+                Overload::try_from(byte(code, value)?).map_err(|_| invalid(code))?,
+            ),
+            OptionCode::DHCPMessageType => DhcpOption::DHCPMessageType(
+                MessageType::try_from(byte(code, value)?).map_err(|_| invalid(code))?,
+            ),
+            OptionCode::ServerIdentifier => DhcpOption::ServerIdentifier(ip(code, value)?),
+            OptionCode::ParameterRequestList => DhcpOption::ParameterRequestList(
+                bytes(code, value, 1)?
+                    .iter()
+                    .map(|b| OptionCode::from_u8(*b))
+                    .collect(),
+            ),
+            OptionCode::Message => DhcpOption::Message(string(code, value)?),
+            OptionCode::MaximumDHCPMessageSize => {
+                DhcpOption::MaximumDHCPMessageSize(short(code, value)?)
+            }
+            OptionCode::RenewalTimeValue => {
+                DhcpOption::RenewalTimeValue(Duration::from_secs(long(code, value)? as u64))
+            }
+            OptionCode::RebindingTimeValue => {
+                DhcpOption::RebindingTimeValue(Duration::from_secs(long(code, value)? as u64))
+            }
+            OptionCode::VendorClassIdentifier => {
+                DhcpOption::VendorClassIdentifier(bytes(code, value, 1)?)
+            }
+            OptionCode::ClientIdentifier => {
+                DhcpOption::ClientIdentifier(ClientIdentifier::decode(value)?)
+            }
+            OptionCode::TFTPServerName => DhcpOption::TFTPServerName(string(code, value)?),
+            OptionCode::BootFileName => DhcpOption::BootFileName(string(code, value)?),
+            OptionCode::UserClass => DhcpOption::UserClass(bytes(code, value, 1)?),
+            OptionCode::ClientArchitecture => {
+                DhcpOption::ClientArchitecture(ProcessorArchitecture::decode_list(value)?)
+            }
+            OptionCode::ClientNetworkInterfaceIdentifier => {
+                DhcpOption::ClientNetworkInterfaceIdentifier(ClientNetworkInterface::decode(value)?)
+            }
+            OptionCode::ClientMachineIdentifier => {
+                DhcpOption::ClientMachineIdentifier(Uuid::decode(value)?)
+            }
+            OptionCode::TZPOSIXString => DhcpOption::TZPOSIXString(string(code, value)?),
+            OptionCode::TZDatabaseString => DhcpOption::TZDatabaseString(string(code, value)?),
+            OptionCode::DomainSearch => {
+                DhcpOption::DomainSearch(DnsName::decode_list(code, value)?)
+            }
+            OptionCode::ClasslessRouteFormat => {
+                DhcpOption::ClasslessRouteFormat(ClasslessRoute::decode_list(code, value)?)
+            }
+            OptionCode::VendorIdentifyingVendorClass => {
+                DhcpOption::VendorIdentifyingVendorClass(VendorClass::decode_list(value)?)
+            }
+            OptionCode::VendorIdentifyingVendorSpecificInformation => {
+                DhcpOption::VendorIdentifyingVendorSpecificInformation(VendorOptions::decode_list(
+                    value,
+                )?)
+            }
+            OptionCode::MSClasslessRouteFormat => {
+                DhcpOption::MSClasslessRouteFormat(ClasslessRoute::decode_list(code, value)?)
+            }
+            // Codes without a typed codec, registered or not
+            _ => DhcpOption::Raw(code, value.to_vec()),
+        })
+    }
+
+    /// Encode the option value, without the code and length bytes.
+    ///
+    /// Fails if a value cannot be represented, such as a relay agent or
+    /// vendor sub-option too long for its length byte.
+    pub fn encode(&self) -> Result<Vec<u8>, OptionError> {
+        Ok(match self {
+            DhcpOption::End => Vec::new(),
+            DhcpOption::Pad => Vec::new(),
+            DhcpOption::SubnetMask(v) => v.octets().to_vec(),
+            DhcpOption::TimeOffset(v) => v.to_be_bytes().to_vec(),
+            DhcpOption::Router(v) => encode_ips(v),
+            DhcpOption::TimeServer(v) => encode_ips(v),
+            DhcpOption::NameServer(v) => encode_ips(v),
+            DhcpOption::DomainNameServer(v) => encode_ips(v),
+            DhcpOption::LogServer(v) => encode_ips(v),
+            DhcpOption::CookieServer(v) => encode_ips(v),
+            DhcpOption::LPRServer(v) => encode_ips(v),
+            DhcpOption::ImpressServer(v) => encode_ips(v),
+            DhcpOption::ResourceLocationServer(v) => encode_ips(v),
+            DhcpOption::HostName(v) => v.as_bytes().to_vec(),
+            DhcpOption::BootFileSize(v) => v.to_be_bytes().to_vec(),
+            DhcpOption::MeritDumpFile(v) => v.as_bytes().to_vec(),
+            DhcpOption::DomainName(v) => v.as_bytes().to_vec(),
+            DhcpOption::SwapServer(v) => v.octets().to_vec(),
+            DhcpOption::RootPath(v) => v.as_bytes().to_vec(),
+            DhcpOption::ExtensionsPath(v) => v.as_bytes().to_vec(),
+            DhcpOption::IPForwardingEnableDisable(v) => vec![*v as u8],
+            DhcpOption::NonLocalSourceRoutingEnableDisable(v) => vec![*v as u8],
+            DhcpOption::PolicyFilter(v) => encode_ip_pairs(v),
+            DhcpOption::MaximumDatagramReassemblySize(v) => v.to_be_bytes().to_vec(),
+            DhcpOption::DefaultIPTimeToLive(v) => vec![*v],
+            DhcpOption::PathMTUAgingTimeout(v) => encode_seconds(*v),
+            DhcpOption::PathMTUPlateauTable(v) => {
+                v.iter().flat_map(|s| s.to_be_bytes().to_vec()).collect()
+            }
+            DhcpOption::InterfaceMTU(v) => v.to_be_bytes().to_vec(),
+            DhcpOption::AllSubnetsAreLocal(v) => vec![*v as u8],
+            DhcpOption::BroadcastAddress(v) => v.octets().to_vec(),
+            DhcpOption::PerformMaskDiscovery(v) => vec![*v as u8],
+            DhcpOption::MaskSupplier(v) => vec![*v as u8],
+            DhcpOption::PerformRouterDiscovery(v) => vec![*v as u8],
+            DhcpOption::RouterSolicitationAddress(v) => v.octets().to_vec(),
+            DhcpOption::StaticRoute(v) => encode_ip_pairs(v),
+            DhcpOption::TrailerEncapsulation(v) => vec![*v as u8],
+            DhcpOption::ARPCacheTimeout(v) => encode_seconds(*v),
+            DhcpOption::EthernetEncapsulation(v) => vec![*v as u8],
+            DhcpOption::TCPDefaultTTL(v) => vec![*v],
+            DhcpOption::TCPKeepaliveInterval(v) => encode_seconds(*v),
+            DhcpOption::TCPKeepaliveGarbage(v) => vec![*v as u8],
+            DhcpOption::NetworkInformationServiceDomain(v) => v.as_bytes().to_vec(),
+            DhcpOption::NetworkInformationServers(v) => encode_ips(v),
+            DhcpOption::NetworkTimeProtocolServers(v) => encode_ips(v),
+            DhcpOption::VendorSpecificInformation(v) => v.clone(),
+            DhcpOption::NetBIOSOverTCPIPNameServer(v) => encode_ips(v),
+            DhcpOption::NetBIOSOverTCPIPDatagramDistributionServer(v) => encode_ips(v),
+            DhcpOption::NetBIOSOverTCPIPNodeType(v) => vec![*v],
+            DhcpOption::NetBIOSOverTCPIPScope(v) => v.as_bytes().to_vec(),
+            DhcpOption::XWindowSystemFontServer(v) => encode_ips(v),
+            DhcpOption::XWindowSystemDisplayManager(v) => encode_ips(v),
+            DhcpOption::NetworkInformationServicePlusDomain(v) => v.as_bytes().to_vec(),
+            DhcpOption::NetworkInformationServicePlusServers(v) => encode_ips(v),
+            DhcpOption::MobileIPHomeAgent(v) => encode_ips(v),
+            DhcpOption::SimpleMailTransportProtocol(v) => encode_ips(v),
+            DhcpOption::PostOfficeProtocolServer(v) => encode_ips(v),
+            DhcpOption::NetworkNewsTransportProtocol(v) => encode_ips(v),
+            DhcpOption::DefaultWorldWideWebServer(v) => encode_ips(v),
+            DhcpOption::DefaultFingerServer(v) => encode_ips(v),
+            DhcpOption::DefaultInternetRelayChatServer(v) => encode_ips(v),
+            DhcpOption::StreetTalkServer(v) => encode_ips(v),
+            DhcpOption::StreetTalkDirectoryAssistance(v) => encode_ips(v),
+            DhcpOption::ClientFQDN(v) => v.encode(),
+            DhcpOption::RelayAgentInformation(v) => v.encode()?,
+            DhcpOption::RequestedIPAddress(v) => v.octets().to_vec(),
+            DhcpOption::IPAddressLeaseTime(v) => encode_seconds(*v),
+            DhcpOption::Overload(v) => vec![*v as u8],
+            DhcpOption::DHCPMessageType(v) => vec![*v as u8],
+            DhcpOption::ServerIdentifier(v) => v.octets().to_vec(),
+            DhcpOption::ParameterRequestList(v) => v.iter().map(|c| u8::from(*c)).collect(),
+            DhcpOption::Message(v) => v.as_bytes().to_vec(),
+            DhcpOption::MaximumDHCPMessageSize(v) => v.to_be_bytes().to_vec(),
+            DhcpOption::RenewalTimeValue(v) => encode_seconds(*v),
+            DhcpOption::RebindingTimeValue(v) => encode_seconds(*v),
+            DhcpOption::VendorClassIdentifier(v) => v.clone(),
+            DhcpOption::ClientIdentifier(v) => v.encode(),
+            DhcpOption::TFTPServerName(v) => v.as_bytes().to_vec(),
+            DhcpOption::BootFileName(v) => v.as_bytes().to_vec(),
+            DhcpOption::UserClass(v) => v.clone(),
+            DhcpOption::ClientArchitecture(v) => ProcessorArchitecture::encode_list(v),
+            DhcpOption::ClientNetworkInterfaceIdentifier(v) => v.encode(),
+            DhcpOption::ClientMachineIdentifier(v) => v.encode(),
+            DhcpOption::TZPOSIXString(v) => v.as_bytes().to_vec(),
+            DhcpOption::TZDatabaseString(v) => v.as_bytes().to_vec(),
+            DhcpOption::DomainSearch(v) => DnsName::encode_list(v),
+            DhcpOption::ClasslessRouteFormat(v) => ClasslessRoute::encode_list(v),
+            DhcpOption::VendorIdentifyingVendorClass(v) => VendorClass::encode_list(v)?,
+            DhcpOption::VendorIdentifyingVendorSpecificInformation(v) => {
+                VendorOptions::encode_list(v)?
+            }
+            DhcpOption::MSClasslessRouteFormat(v) => ClasslessRoute::encode_list(v),
+            DhcpOption::Raw(_, v) => v.clone(),
+        })
+    }
+}
+
+fn invalid(code: OptionCode) -> OptionError {
+    OptionError::InvalidValue { code }
+}
+
+fn check_len(code: OptionCode, value: &[u8], ok: bool) -> Result<(), OptionError> {
+    if ok {
+        Ok(())
+    } else {
+        Err(OptionError::InvalidLength {
+            code,
+            len: value.len(),
+        })
+    }
+}
+
+fn bytes(code: OptionCode, value: &[u8], min: usize) -> Result<Vec<u8>, OptionError> {
+    check_len(code, value, value.len() >= min)?;
+    Ok(value.to_vec())
+}
+
+fn byte(code: OptionCode, value: &[u8]) -> Result<u8, OptionError> {
+    check_len(code, value, value.len() == 1)?;
+    Ok(value[0])
+}
+
+fn flag(code: OptionCode, value: &[u8]) -> Result<bool, OptionError> {
+    match byte(code, value)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(invalid(code)),
+    }
+}
+
+fn short(code: OptionCode, value: &[u8]) -> Result<u16, OptionError> {
+    check_len(code, value, value.len() == 2)?;
+    Ok(((value[0] as u16) << 8) | value[1] as u16)
+}
+
+fn short_list(code: OptionCode, value: &[u8]) -> Result<Vec<u16>, OptionError> {
+    check_len(code, value, !value.is_empty() && value.len() % 2 == 0)?;
+    Ok(value
+        .chunks(2)
+        .map(|c| ((c[0] as u16) << 8) | c[1] as u16)
+        .collect())
+}
+
+fn long(code: OptionCode, value: &[u8]) -> Result<u32, OptionError> {
+    check_len(code, value, value.len() == 4)?;
+    Ok(((value[0] as u32) << 24)
+        | ((value[1] as u32) << 16)
+        | ((value[2] as u32) << 8)
+        | value[3] as u32)
+}
+
+fn ip(code: OptionCode, value: &[u8]) -> Result<Ipv4Addr, OptionError> {
+    check_len(code, value, value.len() == 4)?;
+    Ok(Ipv4Addr::new(value[0], value[1], value[2], value[3]))
+}
+
+fn ip_list(code: OptionCode, value: &[u8], min: usize) -> Result<Vec<Ipv4Addr>, OptionError> {
+    check_len(code, value, value.len() >= min * 4 && value.len() % 4 == 0)?;
+    Ok(value
+        .chunks(4)
+        .map(|c| Ipv4Addr::new(c[0], c[1], c[2], c[3]))
+        .collect())
+}
+
+fn ip_pair_list(code: OptionCode, value: &[u8]) -> Result<Vec<(Ipv4Addr, Ipv4Addr)>, OptionError> {
+    check_len(code, value, !value.is_empty() && value.len() % 8 == 0)?;
+    Ok(value
+        .chunks(8)
+        .map(|c| {
+            (
+                Ipv4Addr::new(c[0], c[1], c[2], c[3]),
+                Ipv4Addr::new(c[4], c[5], c[6], c[7]),
+            )
+        })
+        .collect())
+}
+
+/// Decode an NVT ASCII string. Trailing nulls, which some clients include,
+/// are dropped.
+fn string(code: OptionCode, value: &[u8]) -> Result<String, OptionError> {
+    check_len(code, value, !value.is_empty())?;
+    let end = value.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    String::from_utf8(value[..end].to_vec()).map_err(|_| invalid(code))
+}
+
+fn encode_ips(ips: &[Ipv4Addr]) -> Vec<u8> {
+    ips.iter().flat_map(|ip| ip.octets().to_vec()).collect()
+}
+
+fn encode_ip_pairs(pairs: &[(Ipv4Addr, Ipv4Addr)]) -> Vec<u8> {
+    pairs
+        .iter()
+        .flat_map(|(a, b)| {
+            let mut v = a.octets().to_vec();
+            v.extend_from_slice(&b.octets());
+            v
+        })
+        .collect()
+}
+
+/// Encode a duration as whole seconds, saturating at the 0xffffffff value
+/// that means infinity.
+fn encode_seconds(d: Duration) -> Vec<u8> {
+    (d.as_secs().min(u32::MAX as u64) as u32)
+        .to_be_bytes()
+        .to_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip(code: OptionCode, value: &[u8]) -> DhcpOption {
+        let opt = DhcpOption::decode(code, value).unwrap();
+        assert_eq!(opt.code(), code);
+        assert_eq!(opt.encode(), Ok(value.to_vec()));
+        opt
+    }
+
+    #[test]
+    fn test_decode_values() {
+        assert_eq!(
+            round_trip(OptionCode::SubnetMask, &[255, 255, 255, 0]),
+            DhcpOption::SubnetMask(Ipv4Addr::new(255, 255, 255, 0))
+        );
+        assert_eq!(
+            round_trip(OptionCode::TimeOffset, &[0xff, 0xff, 0xc7, 0xc0]),
+            DhcpOption::TimeOffset(-14400)
+        );
+        assert_eq!(
+            round_trip(OptionCode::DomainNameServer, &[8, 8, 8, 8, 1, 1, 1, 1]),
+            DhcpOption::DomainNameServer(vec![
+                Ipv4Addr::new(8, 8, 8, 8),
+                Ipv4Addr::new(1, 1, 1, 1)
+            ])
+        );
+        assert_eq!(
+            round_trip(OptionCode::StaticRoute, &[10, 0, 0, 0, 192, 168, 1, 1]),
+            DhcpOption::StaticRoute(vec![(
+                Ipv4Addr::new(10, 0, 0, 0),
+                Ipv4Addr::new(192, 168, 1, 1)
+            )])
+        );
+        assert_eq!(
+            round_trip(OptionCode::HostName, b"laptop"),
+            DhcpOption::HostName("laptop".to_owned())
+        );
+        assert_eq!(
+            round_trip(OptionCode::IPAddressLeaseTime, &[0, 1, 0x51, 0x80]),
+            DhcpOption::IPAddressLeaseTime(Duration::from_secs(86400))
+        );
+        assert_eq!(
+            round_trip(OptionCode::InterfaceMTU, &[5, 220]),
+            DhcpOption::InterfaceMTU(1500)
+        );
+        assert_eq!(
+            round_trip(OptionCode::IPForwardingEnableDisable, &[1]),
+            DhcpOption::IPForwardingEnableDisable(true)
+        );
+        assert_eq!(
+            round_trip(OptionCode::DHCPMessageType, &[5]),
+            DhcpOption::DHCPMessageType(MessageType::ACK)
+        );
+        assert_eq!(
+            round_trip(OptionCode::ParameterRequestList, &[1, 3, 224]),
+            DhcpOption::ParameterRequestList(vec![
+                OptionCode::SubnetMask,
+                OptionCode::Router,
+                OptionCode::Other(224)
+            ])
+        );
+        assert_eq!(
+            round_trip(OptionCode::MobileIPHomeAgent, &[]),
+            DhcpOption::MobileIPHomeAgent(Vec::new())
+        );
+        assert_eq!(
+            round_trip(OptionCode::Other(224), &[1, 2]),
+            DhcpOption::Raw(OptionCode::Other(224), vec![1, 2])
+        );
+
+        // Trailing nulls are dropped from strings
+        assert_eq!(
+            DhcpOption::decode(OptionCode::HostName, b"laptop\0").unwrap(),
+            DhcpOption::HostName("laptop".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(
+            DhcpOption::decode(OptionCode::SubnetMask, &[255, 255, 255]),
+            Err(OptionError::InvalidLength {
+                code: OptionCode::SubnetMask,
+                len: 3
+            })
+        );
+        assert_eq!(
+            DhcpOption::decode(OptionCode::Router, &[10, 0, 0, 1, 10]),
+            Err(OptionError::InvalidLength {
+                code: OptionCode::Router,
+                len: 5
+            })
+        );
+        assert_eq!(
+            DhcpOption::decode(OptionCode::Router, &[]),
+            Err(OptionError::InvalidLength {
+                code: OptionCode::Router,
+                len: 0
+            })
+        );
+        assert_eq!(
+            DhcpOption::decode(OptionCode::MaskSupplier, &[2]),
+            Err(OptionError::InvalidValue {
+                code: OptionCode::MaskSupplier
+            })
+        );
+        assert_eq!(
+            DhcpOption::decode(OptionCode::DHCPMessageType, &[9]),
+            Err(OptionError::InvalidValue {
+                code: OptionCode::DHCPMessageType
+            })
+        );
+        assert_eq!(
+            DhcpOption::decode(OptionCode::HostName, &[0xff, 0xfe]),
+            Err(OptionError::InvalidValue {
+                code: OptionCode::HostName
+            })
+        );
+    }
+
+    #[test]
+    fn test_encode_infinite_lease() {
+        let opt = DhcpOption::IPAddressLeaseTime(Duration::from_secs(u64::MAX));
+        assert_eq!(opt.encode(), Ok(vec![0xff, 0xff, 0xff, 0xff]));
+    }
+}
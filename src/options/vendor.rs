@@ -0,0 +1,357 @@
+use super::{OptionCode, OptionError};
+use crate::packet::Packet;
+
+/// Largest payload of one enterprise entry, whose length is a single byte.
+const MAX_ENTRY_LEN: usize = 255;
+
+/// One enterprise's entry in the Vendor-Identifying Vendor Class option
+/// (124, RFC 3925).
+#[derive(PartialEq, Clone, Debug)]
+pub struct VendorClass {
+    /// IANA Private Enterprise Number, e.g. 3561 for the Broadband Forum.
+    pub enterprise_number: u32,
+    /// The vendor class data items, in order.
+    pub data: Vec<Vec<u8>>,
+}
+
+impl VendorClass {
+    pub fn decode_list(value: &[u8]) -> Result<Vec<Self>, OptionError> {
+        let code = OptionCode::VendorIdentifyingVendorClass;
+        let mut list: Vec<VendorClass> = Vec::new();
+
+        for (enterprise_number, mut rest) in decode_entries(code, value)? {
+            let mut data = Vec::new();
+            while !rest.is_empty() {
+                let len = rest[0] as usize;
+                if rest.len() < 1 + len {
+                    return Err(OptionError::InvalidLength {
+                        code,
+                        len: value.len(),
+                    });
+                }
+                data.push(rest[1..1 + len].to_vec());
+                rest = &rest[1 + len..];
+            }
+
+            match list
+                .iter_mut()
+                .find(|c| c.enterprise_number == enterprise_number)
+            {
+                Some(class) => class.data.extend(data),
+                None => list.push(VendorClass {
+                    enterprise_number,
+                    data,
+                }),
+            }
+        }
+
+        Ok(list)
+    }
+
+    /// Fails if an item is longer than 254 bytes, as no entry can hold it.
+    pub fn encode_list(list: &[Self]) -> Result<Vec<u8>, OptionError> {
+        let code = OptionCode::VendorIdentifyingVendorClass;
+        let mut bytes = Vec::new();
+
+        for class in list {
+            let mut items = Vec::new();
+            for item in &class.data {
+                check_item_len(code, item, MAX_ENTRY_LEN - 1)?;
+                let mut framed = vec![item.len() as u8];
+                framed.extend_from_slice(item);
+                items.push(framed);
+            }
+            encode_entries(&mut bytes, class.enterprise_number, items);
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// One enterprise's entry in the Vendor-Identifying Vendor-Specific
+/// Information option (125, RFC 3925): a list of sub-options whose meaning
+/// the enterprise defines.
+#[derive(PartialEq, Clone, Debug)]
+pub struct VendorOptions {
+    /// IANA Private Enterprise Number, e.g. 3561 for the Broadband Forum.
+    pub enterprise_number: u32,
+    /// Sub-options as code and value, in wire order.
+    pub sub_options: Vec<(u8, Vec<u8>)>,
+}
+
+impl VendorOptions {
+    /// The value of the first sub-option with `code`.
+    pub fn get(&self, code: u8) -> Option<&[u8]> {
+        self.sub_options
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, value)| value.as_slice())
+    }
+
+    pub fn decode_list(value: &[u8]) -> Result<Vec<Self>, OptionError> {
+        let code = OptionCode::VendorIdentifyingVendorSpecificInformation;
+        let mut list: Vec<VendorOptions> = Vec::new();
+
+        for (enterprise_number, mut rest) in decode_entries(code, value)? {
+            let mut sub_options = Vec::new();
+            while !rest.is_empty() {
+                if rest.len() < 2 || rest.len() < 2 + rest[1] as usize {
+                    return Err(OptionError::InvalidLength {
+                        code,
+                        len: value.len(),
+                    });
+                }
+                let len = rest[1] as usize;
+                sub_options.push((rest[0], rest[2..2 + len].to_vec()));
+                rest = &rest[2 + len..];
+            }
+
+            match list
+                .iter_mut()
+                .find(|o| o.enterprise_number == enterprise_number)
+            {
+                Some(options) => options.sub_options.extend(sub_options),
+                None => list.push(VendorOptions {
+                    enterprise_number,
+                    sub_options,
+                }),
+            }
+        }
+
+        Ok(list)
+    }
+
+    /// Fails if a sub-option value is longer than 253 bytes, as no entry
+    /// can hold it.
+    pub fn encode_list(list: &[Self]) -> Result<Vec<u8>, OptionError> {
+        let code = OptionCode::VendorIdentifyingVendorSpecificInformation;
+        let mut bytes = Vec::new();
+
+        for options in list {
+            let mut items = Vec::new();
+            for (sub_code, value) in &options.sub_options {
+                check_item_len(code, value, MAX_ENTRY_LEN - 2)?;
+                let mut framed = vec![*sub_code, value.len() as u8];
+                framed.extend_from_slice(value);
+                items.push(framed);
+            }
+            encode_entries(&mut bytes, options.enterprise_number, items);
+        }
+
+        Ok(bytes)
+    }
+}
+
+fn check_item_len(code: OptionCode, item: &[u8], max: usize) -> Result<(), OptionError> {
+    if item.len() > max {
+        return Err(OptionError::InvalidLength {
+            code,
+            len: item.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Split an option value into (enterprise number, data) entries.
+fn decode_entries(code: OptionCode, value: &[u8]) -> Result<Vec<(u32, &[u8])>, OptionError> {
+    let mut entries = Vec::new();
+    let mut rest = value;
+
+    while !rest.is_empty() {
+        if rest.len() < 5 || rest.len() < 5 + rest[4] as usize {
+            return Err(OptionError::InvalidLength {
+                code,
+                len: value.len(),
+            });
+        }
+
+        let enterprise_number = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]);
+        let len = rest[4] as usize;
+        entries.push((enterprise_number, &rest[5..5 + len]));
+        rest = &rest[5 + len..];
+    }
+
+    if entries.is_empty() {
+        return Err(OptionError::InvalidLength { code, len: 0 });
+    }
+
+    Ok(entries)
+}
+
+/// Append entries for `enterprise_number` holding the already framed
+/// `items`, starting a new entry with the same number whenever one fills
+/// up. Decoding merges them back together.
+fn encode_entries<I>(out: &mut Vec<u8>, enterprise_number: u32, items: I)
+where
+    I: IntoIterator<Item = Vec<u8>>,
+{
+    let mut data: Vec<u8> = Vec::new();
+    let flush = |out: &mut Vec<u8>, data: &mut Vec<u8>| {
+        out.extend_from_slice(&enterprise_number.to_be_bytes());
+        out.push(data.len() as u8);
+        out.append(data);
+    };
+
+    for item in items {
+        if data.len() + item.len() > MAX_ENTRY_LEN {
+            flush(out, &mut data);
+        }
+        data.extend_from_slice(&item);
+    }
+    flush(out, &mut data);
+}
+
+impl Packet {
+    /// The Vendor-Identifying Vendor Class entry for `enterprise_number`.
+    ///
+    /// Returns None if the option is absent or has no entry for that
+    /// enterprise.
+    pub fn vendor_class(&self, enterprise_number: u32) -> Option<Result<VendorClass, OptionError>> {
+        self.options
+            .get(OptionCode::VendorIdentifyingVendorClass)
+            .map(VendorClass::decode_list)?
+            .map(|list| {
+                list.into_iter()
+                    .find(|c| c.enterprise_number == enterprise_number)
+            })
+            .transpose()
+    }
+
+    /// The Vendor-Identifying Vendor-Specific Information entry for
+    /// `enterprise_number`.
+    ///
+    /// Returns None if the option is absent or has no entry for that
+    /// enterprise.
+    pub fn vendor_options(
+        &self,
+        enterprise_number: u32,
+    ) -> Option<Result<VendorOptions, OptionError>> {
+        self.options
+            .get(OptionCode::VendorIdentifyingVendorSpecificInformation)
+            .map(VendorOptions::decode_list)?
+            .map(|list| {
+                list.into_iter()
+                    .find(|o| o.enterprise_number == enterprise_number)
+            })
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_vendor_class() {
+        let value = [
+            0, 0, 0x0d, 0xe9, 8, // enterprise 3561
+            3, b'a', b'b', b'c', 3, b'x', b'y', b'z', //
+            0, 0, 0, 9, 2, 1, 0xff, // enterprise 9
+        ];
+
+        let list = VendorClass::decode_list(&value).unwrap();
+        assert_eq!(
+            list,
+            vec![
+                VendorClass {
+                    enterprise_number: 3561,
+                    data: vec![b"abc".to_vec(), b"xyz".to_vec()],
+                },
+                VendorClass {
+                    enterprise_number: 9,
+                    data: vec![vec![0xff]],
+                },
+            ]
+        );
+        assert_eq!(VendorClass::encode_list(&list), Ok(value.to_vec()));
+
+        // An item running past its entry
+        assert_eq!(
+            VendorClass::decode_list(&[0, 0, 0, 9, 2, 5, 0]),
+            Err(OptionError::InvalidLength {
+                code: OptionCode::VendorIdentifyingVendorClass,
+                len: 7
+            })
+        );
+    }
+
+    #[test]
+    fn test_vendor_options() {
+        let value = [
+            0, 0, 0x0d, 0xe9, 9, // enterprise 3561
+            1, 2, b'A', b'B', 4, 3, b'c', b'p', b'e',
+        ];
+
+        let list = VendorOptions::decode_list(&value).unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].enterprise_number, 3561);
+        assert_eq!(list[0].get(4), Some(&b"cpe"[..]));
+        assert_eq!(list[0].get(2), None);
+        assert_eq!(VendorOptions::encode_list(&list), Ok(value.to_vec()));
+
+        assert!(VendorOptions::decode_list(&[0, 0, 0, 9, 1, 1]).is_err());
+        assert!(VendorOptions::decode_list(&[]).is_err());
+    }
+
+    #[test]
+    fn test_long_entries_split() {
+        let options = VendorOptions {
+            enterprise_number: 3561,
+            sub_options: vec![(1, vec![1; 200]), (2, vec![2; 200])],
+        };
+
+        let bytes = VendorOptions::encode_list(std::slice::from_ref(&options)).unwrap();
+        assert_eq!(bytes.len(), 2 * (5 + 202));
+        assert_eq!(VendorOptions::decode_list(&bytes).unwrap(), vec![options]);
+    }
+
+    #[test]
+    fn test_too_long() {
+        let options = VendorOptions {
+            enterprise_number: 3561,
+            sub_options: vec![(1, vec![1; 254])],
+        };
+        assert_eq!(
+            VendorOptions::encode_list(&[options]),
+            Err(OptionError::InvalidLength {
+                code: OptionCode::VendorIdentifyingVendorSpecificInformation,
+                len: 254
+            })
+        );
+
+        let class = VendorClass {
+            enterprise_number: 3561,
+            data: vec![vec![0; 254], vec![1; 254]],
+        };
+        assert!(VendorClass::encode_list(std::slice::from_ref(&class)).is_ok());
+        let class = VendorClass {
+            data: vec![vec![1; 255]],
+            ..class
+        };
+        assert_eq!(
+            VendorClass::encode_list(&[class]),
+            Err(OptionError::InvalidLength {
+                code: OptionCode::VendorIdentifyingVendorClass,
+                len: 255
+            })
+        );
+    }
+
+    #[test]
+    fn test_packet_accessors() {
+        let mut buf = vec![0; 240];
+        buf[..3].copy_from_slice(&[1, 1, 6]);
+        buf[236..].copy_from_slice(&crate::packet::DHCP_COOKIE);
+        buf.extend_from_slice(&[53, 1, 1, 125, 14]);
+        buf.extend_from_slice(&[0, 0, 0, 9, 0]);
+        buf.extend_from_slice(&[0, 0, 0x0d, 0xe9, 4, 1, 2, b'h', b'i']);
+        buf.push(255);
+
+        let packet = Packet::try_from(buf.as_slice()).unwrap();
+        let options = packet.vendor_options(3561).unwrap().unwrap();
+        assert_eq!(options.get(1), Some(&b"hi"[..]));
+        assert!(packet.vendor_options(4491).is_none());
+        assert!(packet.vendor_class(3561).is_none());
+    }
+}
@@ -1,20 +1,30 @@
-use std::collections::HashMap;
-use std::convert::{TryFrom, TryInto};
+use std::convert::TryFrom;
+use std::error::Error;
 use std::fmt;
 use std::net::Ipv4Addr;
 use std::num::ParseIntError;
 use std::str::FromStr;
 
-use crate::options::{MessageType, OptionCode};
+use crate::options::{MessageType, OptionCode, Options, Overload};
+use crate::view::PacketView;
 
 pub const DHCP_COOKIE: [u8; 4] = [99, 130, 83, 99];
 
-type Options = HashMap<OptionCode, Vec<u8>>;
+/// Smallest IP datagram every DHCP client must accept (RFC 2131 section 2).
+pub const MIN_MESSAGE_SIZE: usize = 576;
+
+/// Minimum length of a BOOTP message; shorter replies are zero padded
+/// because some clients and PXE ROMs drop anything smaller (RFC 1542).
+pub const BOOTP_MIN_LEN: usize = 300;
+
+/// Combined size of the IPv4 and UDP headers around a DHCP message.
+const IP_UDP_HEADER_LEN: usize = 28;
 
 #[derive(PartialEq, Clone, Debug)]
 pub struct Packet {
     pub opcode: OpCode,
     pub htype: HardwareType,
+    /// Length of `chaddr` as parsed. Encoding writes `chaddr.len()` instead.
     pub hlen: u8,
     pub hops: u8,
     pub xid: u32,
@@ -29,165 +39,386 @@ pub struct Packet {
     pub file: Vec<u8>,
     pub cookie: [u8; 4],
     pub options: Options,
+    /// Which of `sname` and `file` carried options in the parsed packet.
+    /// Overloaded fields are left empty rather than read as strings and the
+    /// Overload option itself is not kept in `options`.
+    pub overload: Option<Overload>,
 }
 
-impl TryFrom<&[u8]> for Packet {
-    type Error = String;
-
-    fn try_from(src: &[u8]) -> Result<Self, Self::Error> {
-        // Check packet length
-        if src.len() < 240 {
-            return Err("packet too small".to_owned());
-        }
-
-        // Check DHCP cookie value
-        if src[236..240] != DHCP_COOKIE {
-            return Err("DHCP cookie invalid".to_owned());
-        }
+/// Reasons a buffer can be rejected by `Packet::try_from`.
+///
+/// Every variant carries the byte offset into the source buffer where the
+/// problem was found along with the offending value.
+#[derive(PartialEq, Clone, Debug)]
+pub enum ParseError {
+    /// The buffer ended before the fixed 240 byte header and cookie.
+    TooShort { offset: usize, len: usize },
+    /// The magic cookie did not match `DHCP_COOKIE`.
+    BadCookie { offset: usize, value: [u8; 4] },
+    /// The `op` field is neither BOOTREQUEST nor BOOTREPLY.
+    BadOpCode { offset: usize, value: u8 },
+    /// The `htype` field is not a supported hardware type.
+    UnsupportedHardwareType { offset: usize, value: u8 },
+    /// The `hlen` field is longer than the 16 byte `chaddr` field.
+    BadHardwareLength { offset: usize, value: u8 },
+    /// An option's length runs past the end of the buffer.
+    TruncatedOption { offset: usize, code: u8, len: usize },
+    /// The Option Overload option has a value other than 1, 2 or 3.
+    BadOverload { offset: usize, value: u8 },
+}
 
-        // Check hardware address length
-        if src[2] != 6 {
-            return Err("hardware addresses must be 6 bytes".to_owned());
+impl ParseError {
+    /// Byte offset into the source buffer where the error was detected.
+    pub fn offset(&self) -> usize {
+        match *self {
+            ParseError::TooShort { offset, .. }
+            | ParseError::BadCookie { offset, .. }
+            | ParseError::BadOpCode { offset, .. }
+            | ParseError::UnsupportedHardwareType { offset, .. }
+            | ParseError::BadHardwareLength { offset, .. }
+            | ParseError::TruncatedOption { offset, .. }
+            | ParseError::BadOverload { offset, .. } => offset,
         }
-
-        Ok(Packet {
-            opcode: OpCode::try_from(src[0]).map_err(|e| e.to_string())?,
-            htype: HardwareType::try_from(src[1]).map_err(|e| e.to_string())?,
-            hlen: src[2],
-            hops: src[3],
-            xid: bytes_to_u32(&src[4..8]),
-            secs: ((src[8] as u16) << 8) | src[9] as u16,
-            flags: ((src[10] as u16) << 8) | src[11] as u16,
-            ciaddr: bytes_to_ip_addr(&src[12..16]),
-            yiaddr: bytes_to_ip_addr(&src[16..20]),
-            siaddr: bytes_to_ip_addr(&src[20..24]),
-            giaddr: bytes_to_ip_addr(&src[24..28]),
-            chaddr: HardwareAddr::from(&src[28..34]),
-            sname: trim_null(&src[44..108]),
-            file: trim_null(&src[108..236]),
-            cookie: (&src[236..240]).try_into().unwrap(),
-            options: Packet::parse_options(src),
-        })
     }
 }
 
-impl Packet {
-    fn parse_options(src: &[u8]) -> Options {
-        let mut m = HashMap::new();
-
-        if src.len() <= 240 {
-            return m;
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::TooShort { len, .. } => {
+                write!(f, "packet too small: {} bytes, need at least 240", len)
+            }
+            ParseError::BadCookie { offset, value } => {
+                write!(f, "DHCP cookie invalid at offset {}: {:?}", offset, value)
+            }
+            ParseError::BadOpCode { offset, value } => {
+                write!(f, "opcode out of range at offset {}: {}", offset, value)
+            }
+            ParseError::UnsupportedHardwareType { offset, value } => write!(
+                f,
+                "hardware type out of range at offset {}: {}",
+                offset, value
+            ),
+            ParseError::BadHardwareLength { offset, value } => write!(
+                f,
+                "hardware address length invalid at offset {}: {}",
+                offset, value
+            ),
+            ParseError::TruncatedOption { offset, code, len } => write!(
+                f,
+                "option {} at offset {} truncated: length {} exceeds packet",
+                code, offset, len
+            ),
+            ParseError::BadOverload { offset, value } => write!(
+                f,
+                "option overload value invalid at offset {}: {}",
+                offset, value
+            ),
         }
+    }
+}
 
-        let option_bytes_vec = src[240..].to_vec();
-        let mut option_bytes = option_bytes_vec.as_slice();
+impl Error for ParseError {}
 
-        while option_bytes.len() >= 2 {
-            let code = match OptionCode::try_from(option_bytes[0]) {
-                Ok(c) => c,
-                _ => break,
-            };
+/// Reasons `Packet::encode` can fail.
+#[derive(PartialEq, Clone, Debug)]
+pub enum EncodeError {
+    /// The options do not fit in `max_size` bytes even after overloading
+    /// the `file` and `sname` fields. `size` is the unbounded encoded length.
+    TooLarge { size: usize, max_size: usize },
+}
 
-            if code == OptionCode::End {
-                break;
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EncodeError::TooLarge { size, max_size } => {
+                write!(f, "packet too large: {} bytes, limit is {}", size, max_size)
             }
+        }
+    }
+}
 
-            if code == OptionCode::Pad {
-                option_bytes = &option_bytes[1..];
-                continue;
-            }
+impl Error for EncodeError {}
 
-            let size = option_bytes[1] as usize;
-            if option_bytes.len() < size + 2 {
-                break;
-            }
-
-            m.insert(code, option_bytes[2..2 + size].to_vec());
-            option_bytes = &option_bytes[2 + size..];
-        }
+impl TryFrom<&[u8]> for Packet {
+    type Error = ParseError;
 
-        m
+    fn try_from(src: &[u8]) -> Result<Self, Self::Error> {
+        PacketView::new(src).map(|view| Packet::from(&view))
     }
+}
 
+impl Packet {
     pub fn broadcast_flag(&self) -> bool {
         (self.flags >> 8) > 127
     }
 
     pub fn set_broadcast(&mut self, broadcast: bool) {
         if broadcast {
-            self.flags = (1 as u16) << 15;
+            self.flags = 1u16 << 15;
         } else {
             self.flags = 0;
         }
     }
 
     pub fn message_type(&self) -> Option<MessageType> {
-        if let Some(mtype) = self.options.get(&OptionCode::DHCPMessageType) {
+        if let Some(mtype) = self.options.get(OptionCode::DHCPMessageType) {
             if mtype.is_empty() {
                 return None;
             }
 
-            match MessageType::try_from(mtype[0]) {
-                Ok(mt) => Some(mt),
-                Err(_) => None,
-            }
+            MessageType::try_from(mtype[0]).ok()
         } else {
             None
         }
     }
 }
 
-impl From<&Packet> for Vec<u8> {
-    fn from(packet: &Packet) -> Vec<u8> {
-        let mut v = vec![0; 240];
-
-        v[0] = packet.opcode as u8;
-        v[1] = packet.htype as u8;
-        v[2] = packet.hlen as u8;
-        // v[3] hops starts at 0
-        v[4..8].copy_from_slice(&u32_to_bytes(packet.xid));
-        // v[8..10] secs starts at 0, not used
-        v[10] = (packet.flags >> 8) as u8;
-        v[11] = packet.flags as u8;
-        v[12..16].copy_from_slice(&packet.ciaddr.octets());
-        v[16..20].copy_from_slice(&packet.yiaddr.octets());
-        v[20..24].copy_from_slice(&packet.siaddr.octets());
-        v[24..28].copy_from_slice(&packet.giaddr.octets());
-        v[28..34].copy_from_slice(&packet.chaddr.octets());
-
-        for (i, b) in packet.sname.iter().take(64).enumerate() {
-            v[44 + i] = *b;
+impl Packet {
+    /// Largest DHCP message the sender of this packet will accept, taken
+    /// from its Maximum DHCP Message Size option (57) or the 576 byte
+    /// default. The result excludes the IP and UDP headers so it can be
+    /// passed directly to `encode`.
+    pub fn max_message_size(&self) -> usize {
+        let size = match self.options.get(OptionCode::MaximumDHCPMessageSize) {
+            Some(v) if v.len() == 2 => ((v[0] as usize) << 8) | v[1] as usize,
+            _ => MIN_MESSAGE_SIZE,
+        };
+
+        size.max(MIN_MESSAGE_SIZE) - IP_UDP_HEADER_LEN
+    }
+
+    /// Encode the packet in at most `max_size` bytes.
+    ///
+    /// If the options do not fit after the fixed header, they spill into
+    /// the `file` and then `sname` fields, provided those are empty, and an
+    /// Overload option (52) is added to say so. Options are kept whole where
+    /// they fit and are otherwise split across fields per RFC 3396.
+    pub fn encode(&self, max_size: usize) -> Result<Vec<u8>, EncodeError> {
+        let mut v = encode_header(self);
+        let options = format_options(&self.options);
+        let size = v.len() + options.len();
+
+        if size <= max_size {
+            v.extend_from_slice(&options);
+            pad_to_min_len(&mut v, max_size);
+            return Ok(v);
         }
 
-        for (i, b) in packet.file.iter().take(128).enumerate() {
-            v[108 + i] = *b;
+        let too_large = EncodeError::TooLarge { size, max_size };
+
+        // Keep room for the Overload option and End in the options area
+        if max_size < v.len() + 3 + 1 {
+            return Err(too_large);
+        }
+        let mut areas = vec![OptionArea::new(max_size - (v.len() + 3 + 1))];
+        if self.file.is_empty() {
+            areas.push(OptionArea::new(128 - 1));
+        }
+        if self.sname.is_empty() {
+            areas.push(OptionArea::new(64 - 1));
         }
 
-        v[236..240].copy_from_slice(&DHCP_COOKIE);
+        // Receivers concatenate instances of a code in area order, so each
+        // instance goes no earlier than the last area its code was put in
+        let mut first_area = [0; 256];
 
-        v.append(&mut format_options(&packet.options));
+        for (code, value) in wire_order(&self.options) {
+            let code = u8::from(code);
+            let start = first_area[code as usize];
+
+            if value.len() <= 255 {
+                if let Some(i) =
+                    (start..areas.len()).find(|i| areas[*i].remaining() >= 2 + value.len())
+                {
+                    areas[i].push(code, value);
+                    first_area[code as usize] = i;
+                    continue;
+                }
+            }
+
+            let mut rest = value;
+            let mut i = start;
+            while !rest.is_empty() {
+                while i < areas.len() && areas[i].remaining() < 3 {
+                    i += 1;
+                }
+                let area = areas.get_mut(i).ok_or_else(|| too_large.clone())?;
+                let n = rest.len().min(255).min(area.remaining() - 2);
+                area.push(code, &rest[..n]);
+                rest = &rest[n..];
+            }
+            first_area[code as usize] = i;
+        }
+
+        let mut main = areas.remove(0);
+        let file = if self.file.is_empty() && !areas.is_empty() {
+            Some(areas.remove(0))
+        } else {
+            None
+        };
+        let sname = areas.pop();
 
+        let overload = match (
+            file.as_ref().is_some_and(|a| !a.bytes.is_empty()),
+            sname.as_ref().is_some_and(|a| !a.bytes.is_empty()),
+        ) {
+            (true, true) => Overload::Both,
+            (true, false) => Overload::File,
+            (false, true) => Overload::Sname,
+            (false, false) => return Err(too_large),
+        };
+
+        main.bytes
+            .extend_from_slice(&[u8::from(OptionCode::Overload), 1, overload as u8]);
+        main.bytes.push(u8::from(OptionCode::End));
+        v.append(&mut main.bytes);
+
+        if overload.file() {
+            let mut area = file.unwrap();
+            area.bytes.push(u8::from(OptionCode::End));
+            v[108..236].iter_mut().for_each(|b| *b = 0);
+            v[108..108 + area.bytes.len()].copy_from_slice(&area.bytes);
+        }
+
+        if overload.sname() {
+            let mut area = sname.unwrap();
+            area.bytes.push(u8::from(OptionCode::End));
+            v[44..108].iter_mut().for_each(|b| *b = 0);
+            v[44..44 + area.bytes.len()].copy_from_slice(&area.bytes);
+        }
+
+        pad_to_min_len(&mut v, max_size);
+        Ok(v)
+    }
+}
+
+/// A region of the packet being filled with options by `Packet::encode`.
+struct OptionArea {
+    bytes: Vec<u8>,
+    capacity: usize,
+}
+
+impl OptionArea {
+    fn new(capacity: usize) -> Self {
+        OptionArea {
+            bytes: Vec::new(),
+            capacity,
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.capacity - self.bytes.len()
+    }
+
+    fn push(&mut self, code: u8, value: &[u8]) {
+        self.bytes.push(code);
+        self.bytes.push(value.len() as u8);
+        self.bytes.extend_from_slice(value);
+    }
+}
+
+impl From<&Packet> for Vec<u8> {
+    fn from(packet: &Packet) -> Vec<u8> {
+        let mut v = encode_header(packet);
+        v.append(&mut format_options(&packet.options));
+        pad_to_min_len(&mut v, BOOTP_MIN_LEN);
         v
     }
 }
 
+/// Encode the fixed 240 byte BOOTP header and DHCP cookie.
+fn encode_header(packet: &Packet) -> Vec<u8> {
+    let mut v = vec![0; 240];
+
+    v[0] = packet.opcode as u8;
+    v[1] = packet.htype as u8;
+    // The parser takes chaddr's length from hlen, so derive it from the
+    // address rather than trusting the field to agree
+    v[2] = packet.chaddr.len() as u8;
+    // v[3] hops starts at 0
+    v[4..8].copy_from_slice(&u32_to_bytes(packet.xid));
+    // v[8..10] secs starts at 0, not used
+    v[10] = (packet.flags >> 8) as u8;
+    v[11] = packet.flags as u8;
+    v[12..16].copy_from_slice(&packet.ciaddr.octets());
+    v[16..20].copy_from_slice(&packet.yiaddr.octets());
+    v[20..24].copy_from_slice(&packet.siaddr.octets());
+    v[24..28].copy_from_slice(&packet.giaddr.octets());
+    v[28..28 + packet.chaddr.len()].copy_from_slice(packet.chaddr.octets());
+
+    for (i, b) in packet.sname.iter().take(64).enumerate() {
+        v[44 + i] = *b;
+    }
+
+    for (i, b) in packet.file.iter().take(128).enumerate() {
+        v[108 + i] = *b;
+    }
+
+    v[236..240].copy_from_slice(&DHCP_COOKIE);
+
+    v
+}
+
+/// Options in the order they are written on the wire. DHCP Message Type
+/// goes first and Server Identifier second, as some clients expect, and the
+/// rest follow in the order they were added.
+///
+/// Any Overload option is left out, as only the encoder knows whether the
+/// `file` and `sname` fields end up carrying options.
+fn wire_order(options: &Options) -> Vec<(OptionCode, &[u8])> {
+    let rank = |code: OptionCode| match code {
+        OptionCode::DHCPMessageType => 0,
+        OptionCode::ServerIdentifier => 1,
+        _ => 2,
+    };
+
+    // A stable sort keeps everything else in insertion order
+    let mut ordered: Vec<_> = options
+        .iter()
+        .filter(|(c, _)| *c != OptionCode::Overload)
+        .collect();
+    ordered.sort_by_key(|(c, _)| rank(*c));
+    ordered
+}
+
+/// Encode the options area, terminated by End.
 fn format_options(options: &Options) -> Vec<u8> {
     let mut bytes = Vec::new();
 
-    for (code, value) in options {
-        bytes.push(*code as u8);
-        bytes.push(value.len() as u8);
-        bytes.extend_from_slice(&value);
+    for (code, value) in wire_order(options) {
+        if value.is_empty() {
+            bytes.push(u8::from(code));
+            bytes.push(0);
+            continue;
+        }
+
+        // RFC 3396: values longer than 255 bytes are split into several
+        // consecutive instances of the same option
+        for chunk in value.chunks(255) {
+            bytes.push(u8::from(code));
+            bytes.push(chunk.len() as u8);
+            bytes.extend_from_slice(chunk);
+        }
     }
 
+    bytes.push(u8::from(OptionCode::End));
     bytes
 }
 
-fn bytes_to_ip_addr(bytes: &[u8]) -> Ipv4Addr {
+/// Zero pad `v` to the BOOTP minimum length without exceeding `max_size`.
+fn pad_to_min_len(v: &mut Vec<u8>, max_size: usize) {
+    let len = BOOTP_MIN_LEN.min(max_size);
+    if v.len() < len {
+        v.resize(len, 0);
+    }
+}
+
+pub(crate) fn bytes_to_ip_addr(bytes: &[u8]) -> Ipv4Addr {
     Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])
 }
 
-fn bytes_to_u32(bytes: &[u8]) -> u32 {
+pub(crate) fn bytes_to_u32(bytes: &[u8]) -> u32 {
     if bytes.len() == 4 {
         ((bytes[0] as u32) << 24)
             | ((bytes[1] as u32) << 16)
@@ -202,17 +433,10 @@ fn u32_to_bytes(v: u32) -> [u8; 4] {
     [(v >> 24) as u8, (v >> 16) as u8, (v >> 8) as u8, v as u8]
 }
 
-fn trim_null(bytes: &[u8]) -> Vec<u8> {
-    let mut v = Vec::new();
-
-    for b in bytes.iter().cloned() {
-        if b == 0 {
-            break;
-        }
-        v.push(b);
-    }
-
-    v
+/// The bytes of a null padded string field up to the first null.
+pub(crate) fn trim_null(bytes: &[u8]) -> &[u8] {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    &bytes[..end]
 }
 
 #[repr(u8)]
@@ -234,10 +458,48 @@ impl TryFrom<u8> for OpCode {
     }
 }
 
+/// ARP hardware types from the IANA registry, as used in the `htype` field.
 #[repr(u8)]
 #[derive(PartialEq, Clone, Debug, Copy)]
 pub enum HardwareType {
     Ethernet = 1,
+    ExperimentalEthernet = 2,
+    AX25 = 3,
+    ProNETTokenRing = 4,
+    Chaos = 5,
+    IEEE802 = 6,
+    ARCNET = 7,
+    Hyperchannel = 8,
+    Lanstar = 9,
+    AutonetShortAddress = 10,
+    LocalTalk = 11,
+    LocalNet = 12,
+    UltraLink = 13,
+    SMDS = 14,
+    FrameRelay = 15,
+    ATM16 = 16,
+    HDLC = 17,
+    FibreChannel = 18,
+    ATM19 = 19,
+    SerialLine = 20,
+    ATM21 = 21,
+    MILSTD188220 = 22,
+    Metricom = 23,
+    IEEE1394 = 24,
+    MAPOS = 25,
+    Twinaxial = 26,
+    EUI64 = 27,
+    HIPARP = 28,
+    ISO78163 = 29,
+    ARPSec = 30,
+    IPsecTunnel = 31,
+    InfiniBand = 32,
+    TIA102P25CAI = 33,
+    WiegandInterface = 34,
+    PureIP = 35,
+    HWExp1 = 36,
+    HFI = 37,
+    UnifiedBus = 38,
 }
 
 impl TryFrom<u8> for HardwareType {
@@ -246,16 +508,65 @@ impl TryFrom<u8> for HardwareType {
     fn try_from(htype: u8) -> Result<Self, Self::Error> {
         match htype {
             1 => Ok(HardwareType::Ethernet),
+            2 => Ok(HardwareType::ExperimentalEthernet),
+            3 => Ok(HardwareType::AX25),
+            4 => Ok(HardwareType::ProNETTokenRing),
+            5 => Ok(HardwareType::Chaos),
+            6 => Ok(HardwareType::IEEE802),
+            7 => Ok(HardwareType::ARCNET),
+            8 => Ok(HardwareType::Hyperchannel),
+            9 => Ok(HardwareType::Lanstar),
+            10 => Ok(HardwareType::AutonetShortAddress),
+            11 => Ok(HardwareType::LocalTalk),
+            12 => Ok(HardwareType::LocalNet),
+            13 => Ok(HardwareType::UltraLink),
+            14 => Ok(HardwareType::SMDS),
+            15 => Ok(HardwareType::FrameRelay),
+            16 => Ok(HardwareType::ATM16),
+            17 => Ok(HardwareType::HDLC),
+            18 => Ok(HardwareType::FibreChannel),
+            19 => Ok(HardwareType::ATM19),
+            20 => Ok(HardwareType::SerialLine),
+            21 => Ok(HardwareType::ATM21),
+            22 => Ok(HardwareType::MILSTD188220),
+            23 => Ok(HardwareType::Metricom),
+            24 => Ok(HardwareType::IEEE1394),
+            25 => Ok(HardwareType::MAPOS),
+            26 => Ok(HardwareType::Twinaxial),
+            27 => Ok(HardwareType::EUI64),
+            28 => Ok(HardwareType::HIPARP),
+            29 => Ok(HardwareType::ISO78163),
+            30 => Ok(HardwareType::ARPSec),
+            31 => Ok(HardwareType::IPsecTunnel),
+            32 => Ok(HardwareType::InfiniBand),
+            33 => Ok(HardwareType::TIA102P25CAI),
+            34 => Ok(HardwareType::WiegandInterface),
+            35 => Ok(HardwareType::PureIP),
+            36 => Ok(HardwareType::HWExp1),
+            37 => Ok(HardwareType::HFI),
+            38 => Ok(HardwareType::UnifiedBus),
             _ => Err("hardware type out of range"),
         }
     }
 }
 
-#[derive(PartialEq, Clone, Debug, Copy)]
-pub struct HardwareAddr([u8; 6]);
+/// Length of the `chaddr` field, the longest hardware address a packet
+/// can carry.
+pub const MAX_HARDWARE_ADDR_LEN: usize = 16;
+
+/// A client hardware address of up to 16 bytes.
+///
+/// Ethernet addresses are 6 bytes, EUI-64 addresses 8, and InfiniBand
+/// clients send none at all (RFC 4390) and identify themselves with a
+/// client identifier instead.
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Copy)]
+pub struct HardwareAddr {
+    bytes: [u8; MAX_HARDWARE_ADDR_LEN],
+    len: u8,
+}
 
 impl HardwareAddr {
-    /// Get the octets composing the MAC address.
+    /// Get the octets composing the hardware address.
     ///
     /// # Example
     ///
@@ -263,11 +574,21 @@ impl HardwareAddr {
     /// use dhcp_parser::packet::HardwareAddr;
     ///
     /// assert_eq!(
-    /// 	"00-14-22-01-23-45".parse::<HardwareAddr>().unwrap().octets(),
-    /// 	[0, 20, 34, 1, 35, 69]);
+    ///     "00-14-22-01-23-45".parse::<HardwareAddr>().unwrap().octets(),
+    ///     [0, 20, 34, 1, 35, 69]);
     /// ```
-    pub fn octets(self) -> [u8; 6] {
-        self.0
+    pub fn octets(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    /// Number of bytes in the address.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Checks if the address has no bytes, as with InfiniBand clients.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 
     /// Checks if the address is broadcast.
@@ -279,8 +600,8 @@ impl HardwareAddr {
     /// assert!("FF:FF:FF:FF:FF:FF".parse::<HardwareAddr>().unwrap().is_broadcast());
     /// assert!(!"00:00:00:00:00:00".parse::<HardwareAddr>().unwrap().is_broadcast());
     /// ```
-    pub fn is_broadcast(self) -> bool {
-        self.0 == [0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+    pub fn is_broadcast(&self) -> bool {
+        !self.is_empty() && self.octets().iter().all(|b| *b == 0xff)
     }
 }
 
@@ -288,39 +609,54 @@ impl FromStr for HardwareAddr {
     type Err = ParseIntError;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
-        let mut result = [0; 6];
+        let mut result = [0; MAX_HARDWARE_ADDR_LEN];
+        let mut len = 0;
 
-        for (i, byte) in value.split(|c| c == ':' || c == '-').enumerate() {
-            if i > 5 {
-                u8::from_str_radix("error", 10)?;
+        for (i, byte) in value.split([':', '-']).enumerate() {
+            if i >= MAX_HARDWARE_ADDR_LEN {
+                "error".parse::<u8>()?;
             }
 
             result[i] = u8::from_str_radix(byte, 16)?;
+            len = i + 1;
         }
 
-        Ok(HardwareAddr(result))
+        Ok(HardwareAddr {
+            bytes: result,
+            len: len as u8,
+        })
     }
 }
 
 impl From<[u8; 6]> for HardwareAddr {
     fn from(value: [u8; 6]) -> HardwareAddr {
-        HardwareAddr(value)
+        HardwareAddr::from(&value[..])
     }
 }
 
+/// Builds an address from the first 16 bytes of `value`.
 impl<'a> From<&'a [u8]> for HardwareAddr {
     fn from(value: &'a [u8]) -> HardwareAddr {
-        HardwareAddr([value[0], value[1], value[2], value[3], value[4], value[5]])
+        let len = value.len().min(MAX_HARDWARE_ADDR_LEN);
+        let mut bytes = [0; MAX_HARDWARE_ADDR_LEN];
+        bytes[..len].copy_from_slice(&value[..len]);
+
+        HardwareAddr {
+            bytes,
+            len: len as u8,
+        }
     }
 }
 
 impl fmt::Display for HardwareAddr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
-            self.0[0], self.0[1], self.0[2], self.0[3], self.0[4], self.0[5]
-        )
+        for (i, b) in self.octets().iter().enumerate() {
+            if i > 0 {
+                write!(f, ":")?;
+            }
+            write!(f, "{:02X}", b)?;
+        }
+        Ok(())
     }
 }
 
@@ -389,8 +725,9 @@ mod test {
                     120, 121, 122, 123, 124, 125, 109, 110, 111, 112, 113, 114, 115, 116, 117, 118,
                     119, 120, 121, 122, 123, 124, 125, 109,
                 ],
-                cookie: DHCP_COOKIE.clone(),
-                options: HashMap::new(),
+                cookie: DHCP_COOKIE,
+                options: Options::new(),
+                overload: None,
             }
         );
     }
@@ -399,6 +736,482 @@ mod test {
     fn test_format_message() {
         let p = Packet::try_from(TEST_MESSAGE.as_ref()).unwrap();
         let p_bytes: Vec<u8> = (&p).into();
-        assert_eq!(p_bytes, TEST_MESSAGE.as_ref());
+        assert_eq!(p_bytes.len(), BOOTP_MIN_LEN);
+        assert_eq!(p_bytes[..240], TEST_MESSAGE[..]);
+        assert_eq!(p_bytes[240], 255);
+        assert!(p_bytes[241..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Packet::try_from(&TEST_MESSAGE[..100]),
+            Err(ParseError::TooShort {
+                offset: 100,
+                len: 100
+            })
+        );
+
+        let mut bad = TEST_MESSAGE;
+        bad[238] = 0;
+        assert_eq!(
+            Packet::try_from(bad.as_ref()),
+            Err(ParseError::BadCookie {
+                offset: 236,
+                value: [99, 130, 0, 99]
+            })
+        );
+
+        let mut bad = TEST_MESSAGE;
+        bad[0] = 3;
+        assert_eq!(
+            Packet::try_from(bad.as_ref()),
+            Err(ParseError::BadOpCode {
+                offset: 0,
+                value: 3
+            })
+        );
+
+        let mut bad = TEST_MESSAGE;
+        bad[2] = 17;
+        assert_eq!(
+            Packet::try_from(bad.as_ref()),
+            Err(ParseError::BadHardwareLength {
+                offset: 2,
+                value: 17
+            })
+        );
+
+        let mut bad = TEST_MESSAGE.to_vec();
+        bad.extend_from_slice(&[53, 1, 1, 12, 10, b'h', b'o', b's', b't']);
+        assert_eq!(
+            Packet::try_from(bad.as_slice()),
+            Err(ParseError::TruncatedOption {
+                offset: 243,
+                code: 12,
+                len: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_unknown_options_preserved() {
+        let mut msg = TEST_MESSAGE.to_vec();
+        msg.extend_from_slice(&[53, 1, 1, 224, 2, 0xab, 0xcd, 225, 1, 0, 12, 1, b'h', 255]);
+
+        let p = Packet::try_from(msg.as_slice()).unwrap();
+        assert_eq!(p.options.len(), 4);
+        assert_eq!(p.options[OptionCode::Other(224)], vec![0xab, 0xcd]);
+        assert_eq!(p.options[OptionCode::Other(225)], vec![0]);
+        assert_eq!(p.options[OptionCode::HostName], b"h".to_vec());
+
+        let p_bytes: Vec<u8> = (&p).into();
+        assert_eq!(Packet::try_from(p_bytes.as_slice()).unwrap(), p);
+    }
+
+    #[test]
+    fn test_long_options() {
+        let mut msg = TEST_MESSAGE.to_vec();
+        msg.extend_from_slice(&[121, 3, 1, 2, 3, 53, 1, 1, 121, 2, 4, 5, 255]);
+
+        let p = Packet::try_from(msg.as_slice()).unwrap();
+        assert_eq!(
+            p.options[OptionCode::ClasslessRouteFormat],
+            vec![1, 2, 3, 4, 5]
+        );
+
+        let mut p = Packet::try_from(TEST_MESSAGE.as_ref()).unwrap();
+        let long: Vec<u8> = (0..600).map(|i| i as u8).collect();
+        p.options
+            .insert(OptionCode::VendorSpecificInformation, long.clone());
+
+        let p_bytes: Vec<u8> = (&p).into();
+        assert_eq!(p_bytes.len(), 240 + 600 + 3 * 2 + 1);
+        assert_eq!(p_bytes[240..242], [43, 255]);
+        assert_eq!(p_bytes[497..499], [43, 255]);
+        assert_eq!(p_bytes[754..756], [43, 90]);
+
+        let parsed = Packet::try_from(p_bytes.as_slice()).unwrap();
+        assert_eq!(parsed.options[OptionCode::VendorSpecificInformation], long);
+    }
+
+    #[test]
+    fn test_overloaded_options() {
+        let mut msg = TEST_MESSAGE.to_vec();
+        // file carries the lease time, sname carries the host name
+        msg[108..236].iter_mut().for_each(|b| *b = 0);
+        msg[108..115].copy_from_slice(&[51, 4, 0, 0, 0x0e, 0x10, 255]);
+        msg[44..108].iter_mut().for_each(|b| *b = 0);
+        msg[44..51].copy_from_slice(&[12, 4, b'h', b'o', b's', b't', 255]);
+        msg.extend_from_slice(&[53, 1, 1, 52, 1, 3, 255]);
+
+        let p = Packet::try_from(msg.as_slice()).unwrap();
+        assert_eq!(p.overload, Some(Overload::Both));
+        assert!(p.file.is_empty());
+        assert!(p.sname.is_empty());
+        assert_eq!(p.options.len(), 3);
+        assert!(!p.options.contains(OptionCode::Overload));
+        assert_eq!(
+            p.options[OptionCode::IPAddressLeaseTime],
+            vec![0, 0, 0x0e, 0x10]
+        );
+        assert_eq!(p.options[OptionCode::HostName], b"host".to_vec());
+
+        // only the file field is overloaded, sname is still a string
+        let last = msg.len() - 2;
+        msg[last] = 1;
+        let p = Packet::try_from(msg.as_slice()).unwrap();
+        assert_eq!(p.overload, Some(Overload::File));
+        assert_eq!(p.sname, b"\x0c\x04host\xff".to_vec());
+        assert!(!p.options.contains(OptionCode::HostName));
+
+        msg[last] = 4;
+        assert_eq!(
+            Packet::try_from(msg.as_slice()),
+            Err(ParseError::BadOverload {
+                offset: last,
+                value: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_encode_with_overload() {
+        let mut p = Packet::try_from(TEST_MESSAGE.as_ref()).unwrap();
+        p.sname.clear();
+        p.file.clear();
+        p.options.insert(OptionCode::DHCPMessageType, vec![2]);
+        p.options
+            .insert(OptionCode::VendorSpecificInformation, vec![1; 150]);
+        p.options
+            .insert(OptionCode::ClasslessRouteFormat, vec![2; 250]);
+        p.options.insert(OptionCode::HostName, vec![b'h'; 40]);
+
+        assert_eq!(p.max_message_size(), 548);
+        p.options
+            .insert(OptionCode::MaximumDHCPMessageSize, vec![2, 0]);
+        assert_eq!(p.max_message_size(), 548);
+        p.options
+            .insert(OptionCode::MaximumDHCPMessageSize, vec![5, 220]);
+        assert_eq!(p.max_message_size(), 1472);
+        p.options.remove(OptionCode::MaximumDHCPMessageSize);
+
+        let unbounded: Vec<u8> = (&p).into();
+        assert!(unbounded.len() > 548);
+        assert_eq!(p.encode(1500).unwrap(), unbounded);
+
+        let bytes = p.encode(548).unwrap();
+        assert!(bytes.len() <= 548);
+
+        let parsed = Packet::try_from(bytes.as_slice()).unwrap();
+        assert!(parsed.overload.is_some());
+        assert_eq!(parsed.options.len(), p.options.len());
+        for (code, value) in p.options.iter() {
+            assert_eq!(parsed.options.get(code), Some(value));
+        }
+
+        p.options.insert(OptionCode::Message, vec![b'm'; 255]);
+        assert_eq!(
+            p.encode(548),
+            Err(EncodeError::TooLarge {
+                size: unbounded.len() + 257,
+                max_size: 548
+            })
+        );
+    }
+
+    #[test]
+    fn test_encode_limits() {
+        let mut p = Packet::try_from(TEST_MESSAGE.as_ref()).unwrap();
+        p.sname.clear();
+        p.file.clear();
+        p.options.insert(OptionCode::DHCPMessageType, vec![2]);
+
+        // No room for the header, Overload and End
+        assert_eq!(
+            p.encode(242),
+            Err(EncodeError::TooLarge {
+                size: 244,
+                max_size: 242
+            })
+        );
+
+        // A caller's Overload option is replaced by the encoder's own
+        p.options.insert(OptionCode::Overload, vec![3]);
+        let bytes = Vec::from(&p);
+        assert_eq!(bytes[240..244], [53, 1, 2, 255]);
+        assert!(Packet::try_from(bytes.as_slice())
+            .unwrap()
+            .overload
+            .is_none());
+        p.options.remove(OptionCode::Overload);
+
+        // Instances of one code stay in order across the areas: the second
+        // host name part does not fit the options area, so the third must
+        // follow it into `file` even though it would fit
+        p.options.insert(OptionCode::Router, vec![0; 40]);
+        p.options.push(OptionCode::HostName, vec![b'a'; 20]);
+        p.options.push(OptionCode::HostName, vec![b'b'; 60]);
+        p.options.push(OptionCode::HostName, vec![b'c'; 10]);
+        let bytes = p.encode(340).unwrap();
+        assert!(bytes.len() <= 340);
+
+        let parsed = Packet::try_from(bytes.as_slice()).unwrap();
+        let expected = [vec![b'a'; 20], vec![b'b'; 60], vec![b'c'; 10]].concat();
+        assert_eq!(parsed.options[OptionCode::HostName], expected);
+    }
+
+    /// A hand-built DHCPOFFER in the encoder's order: message type first,
+    /// server identifier second, End, then zero padding to 300 bytes.
+    fn offer_message() -> Vec<u8> {
+        [
+            &[
+                2, // op
+                1, // htype
+                6, // hlen
+                0, // hops
+                0x39, 0x03, 0xf3, 0x26, // xid
+                0, 0, // secs
+                0, 0, // flags
+                0, 0, 0, 0, // ciaddr
+                192, 168, 1, 100, // yiaddr
+                192, 168, 1, 1, // siaddr
+                0, 0, 0, 0, // giaddr
+                0x00, 0x05, 0x3c, 0x04, 0x8d, 0x59, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // chaddr
+            ][..],
+            &[0; 64],  // sname
+            &[0; 128], // file
+            &DHCP_COOKIE,
+            &[
+                53, 1, 2, // DHCPMessageType: Offer
+                54, 4, 192, 168, 1, 1, // ServerIdentifier
+                1, 4, 255, 255, 255, 0, // SubnetMask
+                3, 4, 192, 168, 1, 1, // Router
+                6, 8, 192, 168, 1, 1, 8, 8, 8, 8, // DomainNameServer
+                51, 4, 0, 0, 0x0e, 0x10, // IPAddressLeaseTime
+                255,  // End
+            ],
+            &[0; 22],
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_format_wire_order() {
+        // Message type and server identifier are moved to the front, the
+        // rest keep the order they were added in
+        let mut options = Options::new();
+        options.insert(OptionCode::SubnetMask, vec![255, 255, 255, 0]);
+        options.insert(OptionCode::Router, vec![192, 168, 1, 1]);
+        options.insert(OptionCode::ServerIdentifier, vec![192, 168, 1, 1]);
+        options.insert(
+            OptionCode::DomainNameServer,
+            vec![192, 168, 1, 1, 8, 8, 8, 8],
+        );
+        options.insert(OptionCode::IPAddressLeaseTime, vec![0, 0, 0x0e, 0x10]);
+        options.insert(OptionCode::DHCPMessageType, vec![2]);
+
+        let p = Packet {
+            opcode: OpCode::BootReply,
+            htype: HardwareType::Ethernet,
+            hlen: 6,
+            hops: 0,
+            xid: 0x3903f326,
+            secs: 0,
+            flags: 0,
+            ciaddr: Ipv4Addr::UNSPECIFIED,
+            yiaddr: Ipv4Addr::new(192, 168, 1, 100),
+            siaddr: Ipv4Addr::new(192, 168, 1, 1),
+            giaddr: Ipv4Addr::UNSPECIFIED,
+            chaddr: HardwareAddr::from_str("00:05:3c:04:8d:59").unwrap(),
+            sname: Vec::new(),
+            file: Vec::new(),
+            cookie: DHCP_COOKIE,
+            options,
+            overload: None,
+        };
+
+        let expected = offer_message();
+        let p_bytes: Vec<u8> = (&p).into();
+        assert_eq!(p_bytes, expected);
+        assert_eq!(p.encode(p.max_message_size()).unwrap(), expected);
+
+        let parsed = Packet::try_from(expected.as_slice()).unwrap();
+        let codes: Vec<u8> = parsed.options.iter().map(|(c, _)| c.into()).collect();
+        assert_eq!(codes, [53, 54, 1, 3, 6, 51]);
+        assert_eq!(Vec::from(&parsed), expected);
+    }
+
+    /// The DHCPOFFER (frame 2) and DHCPACK (frame 4) sent by the server in
+    /// the `dhcp.pcap` sample capture from the Wireshark wiki, UDP payload
+    /// only. `message_type` is 2 for the offer and 5 for the ack; the frames
+    /// are otherwise identical.
+    fn captured_reply(message_type: u8) -> Vec<u8> {
+        [
+            &[
+                2, 1, 6, 0, // op, htype, hlen, hops
+                0x00, 0x00, 0x3d, 0x1d, // xid
+                0, 0, 0, 0, // secs, flags
+                0, 0, 0, 0, // ciaddr
+                192, 168, 0, 10, // yiaddr
+                192, 168, 0, 1, // siaddr
+                0, 0, 0, 0, // giaddr
+                0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // chaddr
+            ][..],
+            &[0; 64],
+            &[0; 128],
+            &DHCP_COOKIE,
+            &[
+                53,
+                1,
+                message_type, //
+                1,
+                4,
+                255,
+                255,
+                255,
+                0, //
+                58,
+                4,
+                0,
+                0,
+                0x07,
+                0x08, //
+                59,
+                4,
+                0,
+                0,
+                0x0c,
+                0x4e, //
+                51,
+                4,
+                0,
+                0,
+                0x0e,
+                0x10, //
+                54,
+                4,
+                192,
+                168,
+                0,
+                1, //
+                255,
+            ],
+            &[0; 26],
+        ]
+        .concat()
+    }
+
+    /// Split an options area into its options, stopping at End.
+    fn split_options(mut bytes: &[u8]) -> Vec<&[u8]> {
+        let mut options = Vec::new();
+        while bytes[0] != 255 {
+            let len = 2 + bytes[1] as usize;
+            options.push(&bytes[..len]);
+            bytes = &bytes[len..];
+        }
+        options
+    }
+
+    #[test]
+    fn test_captured_replies() {
+        for (message_type, code) in [(MessageType::Offer, 2), (MessageType::ACK, 5)] {
+            let captured = captured_reply(code);
+            let p = Packet::try_from(captured.as_slice()).unwrap();
+            assert_eq!(p.message_type(), Some(message_type));
+
+            let bytes = p.encode(p.max_message_size()).unwrap();
+            assert_eq!(bytes, Vec::from(&p));
+
+            // Same length, header and End offset as the server sent; the
+            // only difference is the server identifier moving up to second
+            assert_eq!(bytes.len(), captured.len());
+            assert_eq!(bytes[..243], captured[..243]);
+            assert_eq!(bytes[243..249], [54, 4, 192, 168, 0, 1]);
+            assert_eq!(bytes[273], 255);
+            assert!(bytes[274..].iter().all(|b| *b == 0));
+
+            let mut sent = split_options(&captured[240..]);
+            let mut ours = split_options(&bytes[240..]);
+            sent.sort_unstable();
+            ours.sort_unstable();
+            assert_eq!(ours, sent);
+        }
+    }
+
+    #[test]
+    fn test_options_container() {
+        let mut o = Options::new();
+        o.insert(OptionCode::HostName, b"a".to_vec());
+        o.insert(OptionCode::Router, vec![10, 0, 0, 1]);
+        o.push(OptionCode::HostName, b"b".to_vec());
+
+        assert_eq!(o.len(), 3);
+        assert_eq!(o.get(OptionCode::HostName), Some(&b"a"[..]));
+        assert_eq!(
+            o.get_all(OptionCode::HostName).collect::<Vec<_>>(),
+            [b"a", b"b"]
+        );
+
+        assert_eq!(
+            o.insert(OptionCode::HostName, b"c".to_vec()),
+            Some(b"a".to_vec())
+        );
+        let codes: Vec<OptionCode> = o.iter().map(|(c, _)| c).collect();
+        assert_eq!(codes, [OptionCode::HostName, OptionCode::Router]);
+
+        assert_eq!(o.remove(OptionCode::HostName), Some(b"c".to_vec()));
+        assert_eq!(o.remove(OptionCode::HostName), None);
+        assert!(!o.contains(OptionCode::HostName));
+        assert_eq!(o.len(), 1);
+    }
+
+    #[test]
+    fn test_hardware_lengths() {
+        // InfiniBand clients leave chaddr empty
+        let mut msg = TEST_MESSAGE;
+        msg[1] = 32;
+        msg[2] = 0;
+        msg[28..44].iter_mut().for_each(|b| *b = 0);
+        let p = Packet::try_from(msg.as_ref()).unwrap();
+        assert_eq!(p.htype, HardwareType::InfiniBand);
+        assert!(p.chaddr.is_empty());
+        assert_eq!(p.chaddr.to_string(), "");
+        assert_eq!(Vec::from(&p)[..240], msg[..]);
+
+        // EUI-64 addresses take 8 bytes
+        let mut msg = TEST_MESSAGE;
+        msg[1] = 27;
+        msg[2] = 8;
+        msg[34..36].copy_from_slice(&[0x35, 0x36]);
+        let p = Packet::try_from(msg.as_ref()).unwrap();
+        assert_eq!(p.htype, HardwareType::EUI64);
+        assert_eq!(p.chaddr.to_string(), "29:30:31:32:33:34:35:36");
+        assert_eq!(
+            p.chaddr,
+            "29:30:31:32:33:34:35:36".parse::<HardwareAddr>().unwrap()
+        );
+        assert_eq!(Vec::from(&p)[..240], msg[..]);
+
+        // An hlen that disagrees with chaddr is corrected on encode
+        let mut p = p;
+        p.hlen = 17;
+        let bytes = Vec::from(&p);
+        assert_eq!(bytes[2], 8);
+        assert_eq!(Packet::try_from(bytes.as_slice()).unwrap().chaddr, p.chaddr);
+
+        let mut msg = TEST_MESSAGE;
+        msg[1] = 0;
+        assert_eq!(
+            Packet::try_from(msg.as_ref()),
+            Err(ParseError::UnsupportedHardwareType {
+                offset: 1,
+                value: 0
+            })
+        );
+
+        assert!("00:11:22:33:44:55:66:77:88:99:aa:bb:cc:dd:ee:ff:00"
+            .parse::<HardwareAddr>()
+            .is_err());
     }
 }
@@ -0,0 +1,108 @@
+//! Receiving with `IP_PKTINFO`, which tells a socket bound to the wildcard
+//! address the interface and destination address of each datagram.
+
+use std::io;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::os::unix::io::RawFd;
+use std::ptr;
+
+use crate::RequestContext;
+
+/// Ask the kernel to attach `IP_PKTINFO` to datagrams received on the IPv4
+/// socket `fd`.
+pub(crate) fn enable(fd: RawFd) -> io::Result<()> {
+    let on: libc::c_int = 1;
+    // SAFETY: `on` outlives the call and its size is passed along with it
+    let ret = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::IPPROTO_IP,
+            libc::IP_PKTINFO,
+            &on as *const libc::c_int as *const libc::c_void,
+            mem::size_of_val(&on) as libc::socklen_t,
+        )
+    };
+
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Receive one datagram into `buf` like `recv_from`, taking the interface
+/// and destination from `IP_PKTINFO` if the kernel attached it.
+pub(crate) fn recv(fd: RawFd, buf: &mut [u8]) -> io::Result<(usize, RequestContext)> {
+    // SAFETY: all zeroes is a valid sockaddr_storage
+    let mut addr: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    // u64s keep the buffer aligned for cmsghdr
+    let mut control = [0u64; 8];
+
+    // SAFETY: all zeroes is a valid msghdr
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_name = &mut addr as *mut libc::sockaddr_storage as *mut libc::c_void;
+    msg.msg_namelen = mem::size_of_val(&addr) as libc::socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = mem::size_of_val(&control) as _;
+
+    // SAFETY: every pointer in `msg` refers to a live buffer of the length
+    // given with it
+    let size = unsafe { libc::recvmsg(fd, &mut msg, 0) };
+    if size < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut context = RequestContext::new(socket_addr(&addr)?);
+
+    // SAFETY: the kernel wrote `msg_controllen` bytes of control messages,
+    // which the CMSG macros stay within
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::IPPROTO_IP && (*cmsg).cmsg_type == libc::IP_PKTINFO {
+                let info = ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::in_pktinfo);
+                context.interface = Some(info.ipi_ifindex as u32);
+                context.dst = Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(
+                    info.ipi_addr.s_addr,
+                ))));
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+
+    Ok((size as usize, context))
+}
+
+fn socket_addr(addr: &libc::sockaddr_storage) -> io::Result<SocketAddr> {
+    match addr.ss_family as libc::c_int {
+        libc::AF_INET => {
+            // SAFETY: the family says the storage holds a sockaddr_in
+            let addr = unsafe { &*(addr as *const _ as *const libc::sockaddr_in) };
+            Ok(SocketAddr::V4(SocketAddrV4::new(
+                Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr)),
+                u16::from_be(addr.sin_port),
+            )))
+        }
+        libc::AF_INET6 => {
+            // SAFETY: the family says the storage holds a sockaddr_in6
+            let addr = unsafe { &*(addr as *const _ as *const libc::sockaddr_in6) };
+            Ok(SocketAddr::V6(SocketAddrV6::new(
+                Ipv6Addr::from(addr.sin6_addr.s6_addr),
+                u16::from_be(addr.sin6_port),
+                addr.sin6_flowinfo,
+                addr.sin6_scope_id,
+            )))
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unsupported source address family",
+        )),
+    }
+}
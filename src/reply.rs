@@ -0,0 +1,398 @@
+use std::error::Error;
+use std::fmt;
+use std::net::Ipv4Addr;
+
+use crate::options::{MessageType, OptionCode, Options};
+use crate::packet::{OpCode, Packet};
+
+/// Reasons a `ReplyBuilder` refuses to build a reply.
+#[derive(PartialEq, Clone, Debug)]
+pub enum ReplyError {
+    /// Only Offer, ACK and NAK are sent by a server in reply to a client.
+    NotAReply(MessageType),
+    /// A header field was set that RFC 2131 Table 3 requires to be zero or
+    /// unused for this reply type.
+    ForbiddenField(&'static str),
+    /// An option was added that must not appear in this reply type, or
+    /// that the builder manages itself.
+    ForbiddenOption(OptionCode),
+    /// A header field required for this reply type was not set.
+    MissingField(&'static str),
+    /// An option required for this reply type was not set.
+    MissingOption(OptionCode),
+}
+
+impl fmt::Display for ReplyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplyError::NotAReply(mtype) => write!(f, "{} is not a server reply", mtype),
+            ReplyError::ForbiddenField(name) => write!(f, "field {} must not be set", name),
+            ReplyError::ForbiddenOption(code) => write!(f, "option {} must not be set", code),
+            ReplyError::MissingField(name) => write!(f, "field {} is required", name),
+            ReplyError::MissingOption(code) => write!(f, "option {} is required", code),
+        }
+    }
+}
+
+impl Error for ReplyError {}
+
+/// Builds an Offer, ACK or NAK from a client's request following the field
+/// and option rules of RFC 2131 Table 3.
+///
+/// The header fields that come from the client (`xid`, `flags`, `giaddr`,
+/// `chaddr` and so on) are copied, the DHCP Message Type and Server
+/// Identifier are set, and the client's Client Identifier (RFC 6842) and
+/// Relay Agent Information (RFC 3046) options are echoed back. Anything the
+/// reply type forbids, such as `yiaddr` in a NAK, is reported by `build`.
+#[derive(Clone, Debug)]
+pub struct ReplyBuilder<'a> {
+    request: &'a Packet,
+    mtype: MessageType,
+    packet: Packet,
+    error: Option<ReplyError>,
+}
+
+impl Packet {
+    /// Start building a `mtype` reply to this request from the server at
+    /// `server_id`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use std::net::Ipv4Addr;
+    /// use dhcp_parser::options::MessageType;
+    /// use dhcp_parser::packet::{OpCode, Packet};
+    ///
+    /// let mut buf = vec![0; 240];
+    /// buf[..3].copy_from_slice(&[1, 1, 6]);
+    /// buf[236..].copy_from_slice(&[99, 130, 83, 99]);
+    /// buf.extend_from_slice(&[53, 1, 1, 255]);
+    /// let discover = Packet::try_from(buf.as_slice()).unwrap();
+    ///
+    /// let offer = discover
+    ///     .reply(MessageType::Offer, Ipv4Addr::new(10, 0, 0, 1))
+    ///     .yiaddr(Ipv4Addr::new(10, 0, 0, 50))
+    ///     .lease_time(3600)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(offer.opcode, OpCode::BootReply);
+    /// assert_eq!(offer.message_type(), Some(MessageType::Offer));
+    ///
+    /// assert!(discover
+    ///     .reply(MessageType::NAK, Ipv4Addr::new(10, 0, 0, 1))
+    ///     .yiaddr(Ipv4Addr::new(10, 0, 0, 50))
+    ///     .build()
+    ///     .is_err());
+    /// ```
+    pub fn reply(&self, mtype: MessageType, server_id: Ipv4Addr) -> ReplyBuilder<'_> {
+        let mut options = Options::new();
+        options.insert(OptionCode::DHCPMessageType, vec![mtype as u8]);
+        options.insert(OptionCode::ServerIdentifier, server_id.octets().to_vec());
+        if let Some(client_id) = self.options.get(OptionCode::ClientIdentifier) {
+            options.insert(OptionCode::ClientIdentifier, client_id.to_vec());
+        }
+
+        // Only an ACK to a REQUEST carries the client's ciaddr
+        let ciaddr = match mtype {
+            MessageType::ACK => self.ciaddr,
+            _ => Ipv4Addr::UNSPECIFIED,
+        };
+
+        ReplyBuilder {
+            request: self,
+            mtype,
+            packet: Packet {
+                opcode: OpCode::BootReply,
+                htype: self.htype,
+                hlen: self.hlen,
+                hops: 0,
+                xid: self.xid,
+                secs: 0,
+                flags: self.flags,
+                ciaddr,
+                yiaddr: Ipv4Addr::UNSPECIFIED,
+                siaddr: Ipv4Addr::UNSPECIFIED,
+                giaddr: self.giaddr,
+                chaddr: self.chaddr,
+                sname: Vec::new(),
+                file: Vec::new(),
+                cookie: self.cookie,
+                options,
+                overload: None,
+            },
+            error: None,
+        }
+    }
+}
+
+impl<'a> ReplyBuilder<'a> {
+    /// Address offered or assigned to the client.
+    pub fn yiaddr(mut self, addr: Ipv4Addr) -> Self {
+        self.packet.yiaddr = addr;
+        self
+    }
+
+    /// Address of the next server in the boot process.
+    pub fn siaddr(mut self, addr: Ipv4Addr) -> Self {
+        self.packet.siaddr = addr;
+        self
+    }
+
+    /// Server host name.
+    pub fn sname(mut self, sname: Vec<u8>) -> Self {
+        self.packet.sname = sname;
+        self
+    }
+
+    /// Boot file name.
+    pub fn file(mut self, file: Vec<u8>) -> Self {
+        self.packet.file = file;
+        self
+    }
+
+    /// Set the IP Address Lease Time option, in seconds.
+    pub fn lease_time(self, secs: u32) -> Self {
+        self.option(OptionCode::IPAddressLeaseTime, secs.to_be_bytes().to_vec())
+    }
+
+    /// Set the Message option, a human readable note for the client.
+    pub fn message(self, message: &str) -> Self {
+        self.option(OptionCode::Message, message.as_bytes().to_vec())
+    }
+
+    /// Set an option. The message type, server identifier and echoed
+    /// options are managed by the builder and cannot be set here.
+    pub fn option(mut self, code: OptionCode, value: Vec<u8>) -> Self {
+        match code {
+            OptionCode::DHCPMessageType
+            | OptionCode::ServerIdentifier
+            | OptionCode::ClientIdentifier
+            | OptionCode::RelayAgentInformation
+            | OptionCode::Overload
+            | OptionCode::Pad
+            | OptionCode::End => {
+                self.error.get_or_insert(ReplyError::ForbiddenOption(code));
+            }
+            _ => {
+                self.packet.options.insert(code, value);
+            }
+        }
+        self
+    }
+
+    /// Check the reply against RFC 2131 Table 3 and return it.
+    pub fn build(mut self) -> Result<Packet, ReplyError> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
+        let inform = self.request.message_type() == Some(MessageType::Inform);
+        let p = &self.packet;
+
+        // Never allowed in a server reply
+        for code in &[
+            OptionCode::RequestedIPAddress,
+            OptionCode::ParameterRequestList,
+            OptionCode::MaximumDHCPMessageSize,
+        ] {
+            if p.options.contains(*code) {
+                return Err(ReplyError::ForbiddenOption(*code));
+            }
+        }
+
+        match self.mtype {
+            MessageType::Offer => {
+                if p.yiaddr.is_unspecified() {
+                    return Err(ReplyError::MissingField("yiaddr"));
+                }
+                if !p.options.contains(OptionCode::IPAddressLeaseTime) {
+                    return Err(ReplyError::MissingOption(OptionCode::IPAddressLeaseTime));
+                }
+            }
+            MessageType::ACK if inform => {
+                if !p.yiaddr.is_unspecified() {
+                    return Err(ReplyError::ForbiddenField("yiaddr"));
+                }
+                if p.options.contains(OptionCode::IPAddressLeaseTime) {
+                    return Err(ReplyError::ForbiddenOption(OptionCode::IPAddressLeaseTime));
+                }
+            }
+            MessageType::ACK => {
+                if p.yiaddr.is_unspecified() {
+                    return Err(ReplyError::MissingField("yiaddr"));
+                }
+                if !p.options.contains(OptionCode::IPAddressLeaseTime) {
+                    return Err(ReplyError::MissingOption(OptionCode::IPAddressLeaseTime));
+                }
+            }
+            MessageType::NAK => {
+                if !p.yiaddr.is_unspecified() {
+                    return Err(ReplyError::ForbiddenField("yiaddr"));
+                }
+                if !p.siaddr.is_unspecified() {
+                    return Err(ReplyError::ForbiddenField("siaddr"));
+                }
+                if !p.sname.is_empty() {
+                    return Err(ReplyError::ForbiddenField("sname"));
+                }
+                if !p.file.is_empty() {
+                    return Err(ReplyError::ForbiddenField("file"));
+                }
+
+                let allowed = |c: OptionCode| {
+                    c == OptionCode::DHCPMessageType
+                        || c == OptionCode::ServerIdentifier
+                        || c == OptionCode::Message
+                        || c == OptionCode::ClientIdentifier
+                        || c == OptionCode::VendorClassIdentifier
+                };
+                if let Some((code, _)) = p.options.iter().find(|(c, _)| !allowed(*c)) {
+                    return Err(ReplyError::ForbiddenOption(code));
+                }
+            }
+            mtype => return Err(ReplyError::NotAReply(mtype)),
+        }
+
+        // Relay agent information goes last (RFC 3046 section 2.2)
+        self.packet.echo_relay_agent_information(self.request);
+
+        Ok(self.packet)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::packet::{HardwareAddr, HardwareType, DHCP_COOKIE};
+
+    const SERVER: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+
+    fn request(mtype: MessageType) -> Packet {
+        let mut options = Options::new();
+        options.insert(OptionCode::DHCPMessageType, vec![mtype as u8]);
+        options.insert(
+            OptionCode::ClientIdentifier,
+            vec![1, 0, 0x11, 0x22, 0x33, 0x44, 0x55],
+        );
+        options.insert(OptionCode::ParameterRequestList, vec![1, 3, 6]);
+        options.insert(OptionCode::RelayAgentInformation, vec![1, 2, 0, 7]);
+
+        Packet {
+            opcode: OpCode::BootRequest,
+            htype: HardwareType::Ethernet,
+            hlen: 6,
+            hops: 1,
+            xid: 0x1234,
+            secs: 3,
+            flags: 0x8000,
+            ciaddr: Ipv4Addr::new(10, 0, 0, 77),
+            yiaddr: Ipv4Addr::UNSPECIFIED,
+            siaddr: Ipv4Addr::UNSPECIFIED,
+            giaddr: Ipv4Addr::new(10, 0, 0, 254),
+            chaddr: HardwareAddr::from([0, 0x11, 0x22, 0x33, 0x44, 0x55]),
+            sname: Vec::new(),
+            file: Vec::new(),
+            cookie: DHCP_COOKIE,
+            options,
+            overload: None,
+        }
+    }
+
+    #[test]
+    fn test_offer() {
+        let req = request(MessageType::Discover);
+        let offer = req
+            .reply(MessageType::Offer, SERVER)
+            .yiaddr(Ipv4Addr::new(10, 0, 0, 50))
+            .lease_time(3600)
+            .option(OptionCode::Router, vec![10, 0, 0, 254])
+            .build()
+            .unwrap();
+
+        assert_eq!(offer.opcode, OpCode::BootReply);
+        assert_eq!(offer.xid, req.xid);
+        assert_eq!(offer.flags, req.flags);
+        assert_eq!(offer.giaddr, req.giaddr);
+        assert_eq!(offer.chaddr, req.chaddr);
+        assert_eq!(offer.hops, 0);
+        assert_eq!(offer.secs, 0);
+        assert!(offer.ciaddr.is_unspecified());
+
+        let codes: Vec<u8> = offer.options.iter().map(|(c, _)| c.into()).collect();
+        assert_eq!(codes, [53, 54, 61, 51, 3, 82]);
+        assert_eq!(offer.options[OptionCode::ServerIdentifier], [10, 0, 0, 1]);
+        assert_eq!(
+            offer.options[OptionCode::IPAddressLeaseTime],
+            [0, 0, 0x0e, 0x10]
+        );
+        assert_eq!(
+            offer.options[OptionCode::RelayAgentInformation],
+            [1, 2, 0, 7]
+        );
+
+        assert_eq!(
+            req.reply(MessageType::Offer, SERVER).lease_time(60).build(),
+            Err(ReplyError::MissingField("yiaddr"))
+        );
+        assert_eq!(
+            req.reply(MessageType::Offer, SERVER)
+                .yiaddr(Ipv4Addr::new(10, 0, 0, 50))
+                .build(),
+            Err(ReplyError::MissingOption(OptionCode::IPAddressLeaseTime))
+        );
+    }
+
+    #[test]
+    fn test_ack() {
+        let req = request(MessageType::Request);
+        let ack = req
+            .reply(MessageType::ACK, SERVER)
+            .yiaddr(Ipv4Addr::new(10, 0, 0, 77))
+            .lease_time(3600)
+            .build()
+            .unwrap();
+        assert_eq!(ack.ciaddr, req.ciaddr);
+
+        let req = request(MessageType::Inform);
+        assert!(req.reply(MessageType::ACK, SERVER).build().is_ok());
+        assert_eq!(
+            req.reply(MessageType::ACK, SERVER).lease_time(3600).build(),
+            Err(ReplyError::ForbiddenOption(OptionCode::IPAddressLeaseTime))
+        );
+    }
+
+    #[test]
+    fn test_nak() {
+        let req = request(MessageType::Request);
+        let nak = req
+            .reply(MessageType::NAK, SERVER)
+            .message("address not available")
+            .build()
+            .unwrap();
+        assert!(nak.ciaddr.is_unspecified());
+        assert!(nak.yiaddr.is_unspecified());
+        assert_eq!(nak.message_type(), Some(MessageType::NAK));
+
+        assert_eq!(
+            req.reply(MessageType::NAK, SERVER)
+                .yiaddr(Ipv4Addr::new(10, 0, 0, 77))
+                .build(),
+            Err(ReplyError::ForbiddenField("yiaddr"))
+        );
+        assert_eq!(
+            req.reply(MessageType::NAK, SERVER).lease_time(60).build(),
+            Err(ReplyError::ForbiddenOption(OptionCode::IPAddressLeaseTime))
+        );
+        assert_eq!(
+            req.reply(MessageType::NAK, SERVER)
+                .option(OptionCode::ServerIdentifier, vec![1, 2, 3, 4])
+                .build(),
+            Err(ReplyError::ForbiddenOption(OptionCode::ServerIdentifier))
+        );
+        assert_eq!(
+            req.reply(MessageType::Discover, SERVER).build(),
+            Err(ReplyError::NotAReply(MessageType::Discover))
+        );
+    }
+}
@@ -0,0 +1,329 @@
+//! An async server loop on tokio, enabled by the `tokio` feature.
+
+use std::future::{self, Future};
+use std::io;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::{pin, Pin};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+
+#[cfg(target_os = "linux")]
+use tokio::io::Interest;
+use tokio::net::UdpSocket;
+use tokio::task::JoinSet;
+
+use crate::packet::Packet;
+use crate::{Request, RequestContext, ServerError};
+
+/// An async handler, for handlers that await lease stores or other I/O.
+///
+/// Implement it with an `async fn`; the future it returns must be `Send`
+/// as each packet is handled on its own task.
+pub trait AsyncPacketHandler: Send + Sync + 'static {
+    fn handle_packet(
+        &self,
+        packet: Packet,
+        context: RequestContext,
+    ) -> impl Future<Output = Option<Packet>> + Send;
+
+    /// Called once when the server stops, after the packets being handled
+    /// have been replied to or dropped. Use it to flush state.
+    fn on_shutdown(&self) {}
+
+    /// Called for every error while serving. The default ignores it, so
+    /// implement this to log errors.
+    fn on_error(&self, _error: &ServerError) {}
+}
+
+pub async fn run_server<H: AsyncPacketHandler>(handler: Arc<H>) -> io::Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:67").await?;
+    socket.set_broadcast(true)?;
+    run_server_with_socket(Arc::new(socket), handler).await
+}
+
+/// Tasks `run_server_with_socket` lets run at once.
+pub const DEFAULT_MAX_TASKS: usize = 1024;
+
+/// Serve requests from `socket` until a fatal error occurs, with at most
+/// `DEFAULT_MAX_TASKS` packets handled at once. See
+/// `run_server_with_shutdown`.
+pub async fn run_server_with_socket<H: AsyncPacketHandler>(
+    socket: Arc<UdpSocket>,
+    handler: Arc<H>,
+) -> io::Result<()> {
+    run_server_with_shutdown(socket, handler, DEFAULT_MAX_TASKS, future::pending()).await
+}
+
+/// Serve requests from `socket` until `shutdown` completes or a fatal error
+/// occurs, routing replies and reporting errors the same way as the
+/// blocking `run_server_with_shutdown`.
+///
+/// Each packet is handled on its own task, so a slow handler does not hold
+/// up other clients. As with several blocking workers, there is no
+/// ordering between packets, even from the same client. While
+/// `max_tasks` packets are being handled, no more are received and new
+/// ones wait in the socket's buffer. Zero is treated as one.
+///
+/// A panic in `handle_packet` is reported to `on_error` as
+/// `ServerError::Panic` and only drops that packet. On shutdown, receiving
+/// stops and this returns `Ok` once the packets being handled have been
+/// replied to. When a fatal error stops the server, they are dropped and
+/// the error is returned. Either way `on_shutdown` is called last.
+pub async fn run_server_with_shutdown<H, F>(
+    socket: Arc<UdpSocket>,
+    handler: Arc<H>,
+    max_tasks: usize,
+    shutdown: F,
+) -> io::Result<()>
+where
+    H: AsyncPacketHandler,
+    F: Future<Output = ()>,
+{
+    #[cfg(target_os = "linux")]
+    {
+        if socket.local_addr()?.is_ipv4() {
+            crate::pktinfo::enable(socket.as_raw_fd())?;
+        }
+    }
+
+    let max_tasks = max_tasks.max(1);
+    let mut shutdown = pin!(shutdown);
+    let mut buf = [0; 1500];
+    let mut tasks = JoinSet::new();
+
+    let result = loop {
+        // Tasks catch handler panics, so there is nothing to check here
+        while tasks.try_join_next().is_some() {}
+
+        let received = if tasks.len() >= max_tasks {
+            tokio::select! {
+                _ = &mut shutdown => break Ok(()),
+                _ = tasks.join_next() => continue,
+            }
+        } else {
+            tokio::select! {
+                _ = &mut shutdown => break Ok(()),
+                received = recv(&socket, &mut buf) => received,
+            }
+        };
+
+        let (size, context) = match received {
+            Ok(received) => received,
+            Err(e) => {
+                let error = ServerError::Receive(e);
+                handler.on_error(&error);
+                if error.is_fatal() {
+                    break Err(error.into());
+                }
+                continue;
+            }
+        };
+
+        let (request, src_packet) =
+            match Request::parse(&buf[..size], context.src, |e| handler.on_error(e)) {
+                Some(parsed) => parsed,
+                None => continue,
+            };
+
+        let socket = socket.clone();
+        let handler = handler.clone();
+        tasks.spawn(async move {
+            let outcome = CatchUnwind(Box::pin(handler.handle_packet(src_packet, context))).await;
+
+            if let Some((data, dst)) = request.reply(outcome, |e| handler.on_error(e)) {
+                if let Err(error) = socket.send_to(data.as_slice(), dst).await {
+                    handler.on_error(&ServerError::Send { dst, error });
+                }
+            }
+        });
+    };
+
+    if result.is_ok() {
+        while tasks.join_next().await.is_some() {}
+    } else {
+        tasks.shutdown().await;
+    }
+
+    handler.on_shutdown();
+    result
+}
+
+/// Receive a datagram with its interface and destination from
+/// `IP_PKTINFO`.
+#[cfg(target_os = "linux")]
+async fn recv(socket: &UdpSocket, buf: &mut [u8]) -> io::Result<(usize, RequestContext)> {
+    socket
+        .async_io(Interest::READABLE, || {
+            crate::pktinfo::recv(socket.as_raw_fd(), buf)
+        })
+        .await
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn recv(socket: &UdpSocket, buf: &mut [u8]) -> io::Result<(usize, RequestContext)> {
+    let (size, src) = socket.recv_from(buf).await?;
+    Ok((size, RequestContext::new(src)))
+}
+
+/// Resolves to the output of a future, or to the panic payload if polling
+/// it panicked.
+struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = thread::Result<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match panic::catch_unwind(AssertUnwindSafe(|| self.0.as_mut().poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+    use std::net::Ipv4Addr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use tokio::sync::oneshot;
+
+    use crate::options::MessageType;
+
+    fn discover(xid: u8) -> Vec<u8> {
+        let mut buf = vec![0; 240];
+        buf[..3].copy_from_slice(&[1, 1, 6]);
+        buf[7] = xid;
+        buf[236..].copy_from_slice(&crate::packet::DHCP_COOKIE);
+        buf.extend_from_slice(&[53, 1, 1, 255]);
+        buf
+    }
+
+    fn offer(packet: Packet) -> Option<Packet> {
+        packet
+            .reply(MessageType::Offer, Ipv4Addr::new(127, 0, 0, 1))
+            .yiaddr(Ipv4Addr::new(10, 0, 0, 50))
+            .lease_time(3600)
+            .build()
+            .ok()
+    }
+
+    async fn recv(client: &UdpSocket) -> (Packet, std::net::SocketAddr) {
+        let mut buf = [0; 1500];
+        let (size, from) = tokio::time::timeout(Duration::from_secs(5), client.recv_from(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        (Packet::try_from(&buf[..size]).unwrap(), from)
+    }
+
+    struct Offer;
+
+    impl AsyncPacketHandler for Offer {
+        async fn handle_packet(&self, packet: Packet, context: RequestContext) -> Option<Packet> {
+            assert!(context.src.ip().is_loopback());
+            if cfg!(target_os = "linux") {
+                assert!(context.interface.is_some());
+                assert_eq!(context.dst, Some(Ipv4Addr::LOCALHOST.into()));
+            }
+            tokio::task::yield_now().await;
+            offer(packet)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_server() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+        tokio::spawn(run_server_with_socket(Arc::new(server), Arc::new(Offer)));
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.send_to(&discover(42), server_addr).await.unwrap();
+
+        let (offer, from) = recv(&client).await;
+        assert_eq!(from, server_addr);
+        assert_eq!(offer.xid, 42);
+        assert_eq!(offer.message_type(), Some(MessageType::Offer));
+        assert_eq!(offer.yiaddr, Ipv4Addr::new(10, 0, 0, 50));
+    }
+
+    /// Offers after a delay, tracking how many packets are handled at once.
+    /// Panics on the packet with xid 1.
+    struct Tracked {
+        active: AtomicUsize,
+        max_active: AtomicUsize,
+        errors: Mutex<Vec<String>>,
+        shutdowns: AtomicUsize,
+    }
+
+    impl AsyncPacketHandler for Tracked {
+        async fn handle_packet(&self, packet: Packet, _context: RequestContext) -> Option<Packet> {
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_active.fetch_max(active, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.active.fetch_sub(1, Ordering::SeqCst);
+
+            assert_ne!(packet.xid, 1, "bad packet");
+            offer(packet)
+        }
+
+        fn on_error(&self, error: &ServerError) {
+            self.errors.lock().unwrap().push(error.to_string());
+        }
+
+        fn on_shutdown(&self) {
+            self.shutdowns.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limits_panics_and_shutdown() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let handler = Arc::new(Tracked {
+            active: AtomicUsize::new(0),
+            max_active: AtomicUsize::new(0),
+            errors: Mutex::new(Vec::new()),
+            shutdowns: AtomicUsize::new(0),
+        });
+        let (stop, stopped) = oneshot::channel();
+        let server = tokio::spawn(run_server_with_shutdown(
+            Arc::new(server),
+            handler.clone(),
+            2,
+            async move {
+                stopped.await.ok();
+            },
+        ));
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        for xid in 0..6 {
+            client.send_to(&discover(xid), server_addr).await.unwrap();
+        }
+
+        let mut xids = Vec::new();
+        for _ in 0..5 {
+            xids.push(recv(&client).await.0.xid);
+        }
+        xids.sort_unstable();
+        assert_eq!(xids, [0, 2, 3, 4, 5]);
+        assert_eq!(handler.max_active.load(Ordering::SeqCst), 2);
+
+        let errors = handler.errors.lock().unwrap().clone();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("handler panicked on packet from 127.0.0.1"));
+        assert!(errors[0].contains("bad packet"));
+
+        assert_eq!(handler.shutdowns.load(Ordering::SeqCst), 0);
+        stop.send(()).unwrap();
+        assert!(server.await.unwrap().is_ok());
+        assert_eq!(handler.shutdowns.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,411 @@
+use std::convert::TryFrom;
+use std::net::Ipv4Addr;
+
+use crate::options::{MessageType, OptionCode, Options, Overload};
+use crate::packet::{
+    bytes_to_ip_addr, bytes_to_u32, trim_null, HardwareAddr, HardwareType, OpCode, Packet,
+    ParseError, DHCP_COOKIE, MAX_HARDWARE_ADDR_LEN,
+};
+
+/// A read-only view of a DHCP packet borrowed from a receive buffer.
+///
+/// `PacketView::new` checks the header and the framing of every option up
+/// front without allocating, so the accessors cannot fail. Fields are read
+/// from the buffer on demand. Use `Packet::from` to get an owned copy when
+/// the packet needs to outlive the buffer or be modified.
+#[derive(Clone, Copy, Debug)]
+pub struct PacketView<'a> {
+    buf: &'a [u8],
+    opcode: OpCode,
+    htype: HardwareType,
+    overload: Option<Overload>,
+}
+
+impl<'a> PacketView<'a> {
+    pub fn new(buf: &'a [u8]) -> Result<Self, ParseError> {
+        // Check packet length
+        if buf.len() < 240 {
+            return Err(ParseError::TooShort {
+                offset: buf.len(),
+                len: buf.len(),
+            });
+        }
+
+        // Check DHCP cookie value
+        if buf[236..240] != DHCP_COOKIE {
+            return Err(ParseError::BadCookie {
+                offset: 236,
+                value: [buf[236], buf[237], buf[238], buf[239]],
+            });
+        }
+
+        let opcode = OpCode::try_from(buf[0]).map_err(|_| ParseError::BadOpCode {
+            offset: 0,
+            value: buf[0],
+        })?;
+
+        let htype =
+            HardwareType::try_from(buf[1]).map_err(|_| ParseError::UnsupportedHardwareType {
+                offset: 1,
+                value: buf[1],
+            })?;
+
+        // Check hardware address length
+        if buf[2] as usize > MAX_HARDWARE_ADDR_LEN {
+            return Err(ParseError::BadHardwareLength {
+                offset: 2,
+                value: buf[2],
+            });
+        }
+
+        // Overload is only meaningful in the options area, and the fields
+        // it names are read file first, then sname (RFC 2131 section 4.1)
+        let overload = check_option_field(buf, 240, buf.len())?;
+        if let Some(ov) = overload {
+            if ov.file() {
+                check_option_field(buf, 108, 236)?;
+            }
+            if ov.sname() {
+                check_option_field(buf, 44, 108)?;
+            }
+        }
+
+        Ok(PacketView {
+            buf,
+            opcode,
+            htype,
+            overload,
+        })
+    }
+
+    /// The underlying buffer.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    pub fn opcode(&self) -> OpCode {
+        self.opcode
+    }
+
+    pub fn htype(&self) -> HardwareType {
+        self.htype
+    }
+
+    pub fn hlen(&self) -> u8 {
+        self.buf[2]
+    }
+
+    pub fn hops(&self) -> u8 {
+        self.buf[3]
+    }
+
+    pub fn xid(&self) -> u32 {
+        bytes_to_u32(&self.buf[4..8])
+    }
+
+    pub fn secs(&self) -> u16 {
+        ((self.buf[8] as u16) << 8) | self.buf[9] as u16
+    }
+
+    pub fn flags(&self) -> u16 {
+        ((self.buf[10] as u16) << 8) | self.buf[11] as u16
+    }
+
+    pub fn broadcast_flag(&self) -> bool {
+        self.buf[10] > 127
+    }
+
+    pub fn ciaddr(&self) -> Ipv4Addr {
+        bytes_to_ip_addr(&self.buf[12..16])
+    }
+
+    pub fn yiaddr(&self) -> Ipv4Addr {
+        bytes_to_ip_addr(&self.buf[16..20])
+    }
+
+    pub fn siaddr(&self) -> Ipv4Addr {
+        bytes_to_ip_addr(&self.buf[20..24])
+    }
+
+    pub fn giaddr(&self) -> Ipv4Addr {
+        bytes_to_ip_addr(&self.buf[24..28])
+    }
+
+    pub fn chaddr(&self) -> HardwareAddr {
+        HardwareAddr::from(&self.buf[28..28 + self.hlen() as usize])
+    }
+
+    /// Server host name up to the first null byte, or empty if the field
+    /// carries options.
+    pub fn sname(&self) -> &'a [u8] {
+        if self.overload.is_some_and(Overload::sname) {
+            return &[];
+        }
+        trim_null(&self.buf[44..108])
+    }
+
+    /// Boot file name up to the first null byte, or empty if the field
+    /// carries options.
+    pub fn file(&self) -> &'a [u8] {
+        if self.overload.is_some_and(Overload::file) {
+            return &[];
+        }
+        trim_null(&self.buf[108..236])
+    }
+
+    pub fn overload(&self) -> Option<Overload> {
+        self.overload
+    }
+
+    /// Iterate over every option instance on the wire, in the options area
+    /// and then any overloaded fields. Unlike `Packet::options`, repeated
+    /// instances are returned separately rather than concatenated.
+    pub fn options(&self) -> OptionIter<'a> {
+        let mut areas = [(240, self.buf.len()), (0, 0), (0, 0)];
+        let mut count = 1;
+
+        if let Some(ov) = self.overload {
+            if ov.file() {
+                areas[count] = (108, 236);
+                count += 1;
+            }
+            if ov.sname() {
+                areas[count] = (44, 108);
+                count += 1;
+            }
+        }
+
+        OptionIter {
+            buf: self.buf,
+            areas,
+            count,
+            area: 0,
+            offset: 240,
+        }
+    }
+
+    /// Value of the first instance of `code`.
+    pub fn option(&self, code: OptionCode) -> Option<&'a [u8]> {
+        self.options().find(|(c, _)| *c == code).map(|(_, v)| v)
+    }
+
+    pub fn message_type(&self) -> Option<MessageType> {
+        self.option(OptionCode::DHCPMessageType)
+            .and_then(|v| v.first())
+            .and_then(|b| MessageType::try_from(*b).ok())
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for PacketView<'a> {
+    type Error = ParseError;
+
+    fn try_from(buf: &'a [u8]) -> Result<Self, Self::Error> {
+        PacketView::new(buf)
+    }
+}
+
+impl From<&PacketView<'_>> for Packet {
+    fn from(view: &PacketView<'_>) -> Packet {
+        let mut options = Options::new();
+
+        for (code, value) in view.options() {
+            if code == OptionCode::Overload {
+                continue;
+            }
+
+            // RFC 3396: repeated instances of an option are concatenated
+            match options.get_mut(code) {
+                Some(v) => v.extend_from_slice(value),
+                None => options.push(code, value.to_vec()),
+            }
+        }
+
+        Packet {
+            opcode: view.opcode(),
+            htype: view.htype(),
+            hlen: view.hlen(),
+            hops: view.hops(),
+            xid: view.xid(),
+            secs: view.secs(),
+            flags: view.flags(),
+            ciaddr: view.ciaddr(),
+            yiaddr: view.yiaddr(),
+            siaddr: view.siaddr(),
+            giaddr: view.giaddr(),
+            chaddr: view.chaddr(),
+            sname: view.sname().to_vec(),
+            file: view.file().to_vec(),
+            cookie: DHCP_COOKIE,
+            options,
+            overload: view.overload(),
+        }
+    }
+}
+
+/// Iterator over the option instances of a `PacketView`.
+#[derive(Clone, Debug)]
+pub struct OptionIter<'a> {
+    buf: &'a [u8],
+    areas: [(usize, usize); 3],
+    count: usize,
+    area: usize,
+    offset: usize,
+}
+
+impl<'a> Iterator for OptionIter<'a> {
+    type Item = (OptionCode, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.area < self.count {
+            let end = self.areas[self.area].1;
+
+            if self.offset >= end || self.buf[self.offset] == u8::from(OptionCode::End) {
+                self.area += 1;
+                if self.area < self.count {
+                    self.offset = self.areas[self.area].0;
+                }
+                continue;
+            }
+
+            if self.buf[self.offset] == u8::from(OptionCode::Pad) {
+                self.offset += 1;
+                continue;
+            }
+
+            // Framing was checked by PacketView::new
+            let code = OptionCode::from_u8(self.buf[self.offset]);
+            let size = self.buf[self.offset + 1] as usize;
+            let value = &self.buf[self.offset + 2..self.offset + 2 + size];
+            self.offset += 2 + size;
+
+            return Some((code, value));
+        }
+
+        None
+    }
+}
+
+/// Check that the options in `buf[start..end]` are well formed, stopping at
+/// End. Returns the value of the first Overload option found.
+fn check_option_field(
+    buf: &[u8],
+    start: usize,
+    end: usize,
+) -> Result<Option<Overload>, ParseError> {
+    let mut offset = start;
+    let mut overload = None;
+
+    while offset < end {
+        let code = OptionCode::from_u8(buf[offset]);
+
+        if code == OptionCode::End {
+            break;
+        }
+
+        if code == OptionCode::Pad {
+            offset += 1;
+            continue;
+        }
+
+        if offset + 1 >= end {
+            return Err(ParseError::TruncatedOption {
+                offset,
+                code: buf[offset],
+                len: 0,
+            });
+        }
+
+        let size = buf[offset + 1] as usize;
+        if end < offset + 2 + size {
+            return Err(ParseError::TruncatedOption {
+                offset,
+                code: buf[offset],
+                len: size,
+            });
+        }
+
+        if code == OptionCode::Overload && size > 0 && overload.is_none() {
+            let value = buf[offset + 2];
+            overload = Some(
+                Overload::try_from(value).map_err(|_| ParseError::BadOverload {
+                    offset: offset + 2,
+                    value,
+                })?,
+            );
+        }
+
+        offset += 2 + size;
+    }
+
+    Ok(overload)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn discover() -> Vec<u8> {
+        let mut msg = vec![0; 240];
+        msg[..4].copy_from_slice(&[1, 1, 6, 0]);
+        msg[4..8].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        msg[10] = 0x80;
+        msg[28..34].copy_from_slice(&[0, 0x11, 0x22, 0x33, 0x44, 0x55]);
+        msg[236..240].copy_from_slice(&DHCP_COOKIE);
+        msg.extend_from_slice(&[
+            53, 1, 1, // DHCPMessageType: Discover
+            12, 3, b'a', b'b', b'c', // HostName
+            0,    // Pad
+            12, 2, b'd', b'e', // HostName, continued
+            55, 3, 1, 3, 6, // ParameterRequestList
+            255,
+        ]);
+        msg
+    }
+
+    #[test]
+    fn test_view_fields() {
+        let msg = discover();
+        let view = PacketView::new(&msg).unwrap();
+
+        assert_eq!(view.opcode(), OpCode::BootRequest);
+        assert_eq!(view.xid(), 0xdeadbeef);
+        assert!(view.broadcast_flag());
+        assert_eq!(view.chaddr().to_string(), "00:11:22:33:44:55");
+        assert!(view.sname().is_empty());
+        assert_eq!(view.message_type(), Some(MessageType::Discover));
+
+        let options: Vec<_> = view.options().collect();
+        assert_eq!(
+            options,
+            [
+                (OptionCode::DHCPMessageType, &[1][..]),
+                (OptionCode::HostName, &b"abc"[..]),
+                (OptionCode::HostName, &b"de"[..]),
+                (OptionCode::ParameterRequestList, &[1, 3, 6][..]),
+            ]
+        );
+        assert_eq!(view.option(OptionCode::HostName), Some(&b"abc"[..]));
+
+        let p = Packet::from(&view);
+        assert_eq!(p, Packet::try_from(msg.as_slice()).unwrap());
+        assert_eq!(p.options[OptionCode::HostName], b"abcde"[..]);
+        assert_eq!(p.xid, view.xid());
+    }
+
+    #[test]
+    fn test_view_overload_order() {
+        let mut msg = discover();
+        msg.truncate(msg.len() - 1);
+        msg.extend_from_slice(&[52, 1, 3, 255]);
+        msg[44..48].copy_from_slice(&[60, 1, b's', 255]);
+        msg[108..112].copy_from_slice(&[60, 1, b'f', 255]);
+
+        let view = PacketView::new(&msg).unwrap();
+        let codes: Vec<u8> = view.options().map(|(c, _)| c.into()).collect();
+        assert_eq!(codes, [53, 12, 12, 55, 52, 60, 60]);
+        assert!(view.file().is_empty());
+
+        let p = Packet::from(&view);
+        assert_eq!(p.options[OptionCode::VendorClassIdentifier], b"fs"[..]);
+    }
+}